@@ -0,0 +1,69 @@
+# Deutsch (Deutschland)
+
+language-name = Deutsch
+
+## Menüs
+
+game-title = It's Just Business
+menu-play = Spielen
+menu-settings = Einstellungen
+menu-exit = Beenden
+menu-back = Zurück
+menu-audio = Audio
+pause-title = Pause
+menu-resume = Weiter
+menu-restart = Nacht neu starten
+menu-title = Zum Titelbildschirm
+results-finished = Nacht geschafft
+results-shift-over = Schichtende
+results-exhausted = Cliff ist umgekippt
+results-tasks = Aufgaben: { $count } / { $total }
+results-time = Arbeitszeit: { $hours }:{ $minutes }
+results-earnings = Verdienst: { $amount } $
+results-damages = Schäden: { $amount } $
+results-grade = Note: { $grade }
+menu-retry = Nacht wiederholen
+menu-next-level = Nächstes Level
+menu-language = Sprache: { language-name }
+menu-game-speed = Spieltempo: { $speed }x
+audio-master = Gesamt: { $level } %
+audio-music = Musik: { $level } %
+audio-effects = Effekte: { $level } %
+audio-menus = Menüs: { $level } %
+audio-mute = Stumm
+audio-unmute = Ton an
+
+## Dialog
+
+speaker-cliff = Cliff
+
+## HUD
+
+hud-tasks = AUFGABEN ERLEDIGT: { $count } / { $total }
+hud-time = ZEIT: { $time }
+hud-level = LEVEL { $level }
+clock-24h = { $hours }:{ $minutes } Uhr
+
+## Einleitung
+
+welcome-time = Es ist { $time }.
+welcome-alone = Alle haben das Büro verlassen.
+welcome-cliff = Du bist Cliff, ein alter Hausmeister.
+welcome-cliff-reply = Noch eine Nacht, noch mehr Dreck. Na dann los.
+welcome-job = Deine Aufgabe ist es, das Büro zu putzen.
+welcome-controls = Bewege dich mit den Pfeiltasten.
+welcome-hint = Am Ende der Nacht ist das Büro vielleicht nicht nur sauber.
+welcome-twist = Es könnte dir gehören.
+
+## Ereignisse
+
+event-phone-ring = In einem der Büros klingelt ein Telefon.
+event-alarm = In der Lobby geht ein Alarm los!
+event-thunder = Donner. Das Dach ist schon wieder undicht.
+event-door-creak = Eine Tür knarrt... nasse Fußspuren in der Lobby.
+event-cat-meow = Die Bürokatze hat im Pausenraum einen Mülleimer umgeworfen.
+event-phone-answered = Du hast eine Nachricht für den Chef notiert. Gut gemacht.
+event-phone-missed = Das Telefon hat aufgehört zu klingeln. Das war vielleicht wichtig.
+event-alarm-off = Du hast den Alarm ausgeschaltet.
+
+door-locked = Diese Tür ist abgeschlossen. Jemand anderes hat den Schlüssel.
@@ -0,0 +1,70 @@
+# English (United States)
+
+language-name = English
+
+## Menus
+
+game-title = It's Just Business
+menu-play = Play
+menu-settings = Settings
+menu-exit = Exit
+menu-back = Back
+menu-audio = Audio
+pause-title = Paused
+menu-resume = Resume
+menu-restart = Restart Night
+menu-title = Return to Title
+results-finished = Night Complete
+results-shift-over = Shift Over
+results-exhausted = Cliff Passed Out
+results-tasks = Tasks: { $count } / { $total }
+results-time = Time on the clock: { $hours }:{ $minutes }
+results-earnings = Earnings: ${ $amount }
+results-damages = Damages: ${ $amount }
+results-grade = Grade: { $grade }
+menu-retry = Retry Night
+menu-next-level = Next Level
+menu-language = Language: { language-name }
+menu-game-speed = Game Speed: { $speed }x
+audio-master = Master: { $level }%
+audio-music = Music: { $level }%
+audio-effects = Effects: { $level }%
+audio-menus = Menus: { $level }%
+audio-mute = Mute
+audio-unmute = Unmute
+
+## Dialogue
+
+speaker-cliff = Cliff
+
+## HUD
+
+hud-tasks = TASKS COMPLETED: { $count } / { $total }
+hud-time = TIME: { $time }
+hud-level = LEVEL { $level }
+clock-am = { $hours }:{ $minutes } A.M.
+clock-pm = { $hours }:{ $minutes } P.M.
+
+## Opening
+
+welcome-time = It is { $time }.
+welcome-alone = Everyone has left the office.
+welcome-cliff = You are an old janitor, Cliff.
+welcome-cliff-reply = Another night, another mess. Let's get to it.
+welcome-job = Your job is to clean the office.
+welcome-controls = Use the arrow keys to move around.
+welcome-hint = By the end of the night, the office might not just be clean.
+welcome-twist = It might be yours.
+
+## Night events
+
+event-phone-ring = A phone is ringing in one of the offices.
+event-alarm = An alarm is going off in the lobby!
+event-thunder = Thunder. The roof is leaking again.
+event-door-creak = A door creaks open... wet footprints in the lobby.
+event-cat-meow = The office cat knocked over a bin in the break room.
+event-phone-answered = You took a message for the boss. Nice.
+event-phone-missed = The phone stopped ringing. That might have been important.
+event-alarm-off = You switched the alarm off.
+
+door-locked = This door is locked. Someone else has the key.
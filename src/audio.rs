@@ -1,8 +1,13 @@
 use bevy::prelude::*;
+use bevy::utils::HashMap;
 use bevy_kira_audio::prelude::*;
-use bevy_kira_audio::{Audio, AudioChannel};
-use crate::GameState;
-use crate::text::ButtonPressState;
+use bevy_kira_audio::AudioChannel;
+use bevy_spritesheet_animation::prelude::{AnimationEvent, SpritesheetLibrary};
+use crate::input::{GameAction, GameInput};
+use crate::rng::GameRng;
+use crate::settings::UserSettings;
+use crate::text::PopupState;
+use crate::{PosVar, FOOTSTEP_MARKER};
 
 pub struct GameAudioPlugin;
 
@@ -12,44 +17,390 @@ pub struct Background;
 #[derive(Resource)]
 pub struct ButtonPress;
 
+#[derive(Resource)]
+pub struct Effects;
+
+// Channel levels at full bus volume; the mixer scales down from here.
+const MUSIC_MIX: f64 = 0.05;
+const SFX_MIX: f64 = 1.0;
+const UI_MIX: f64 = 0.15;
+
+// Music drops to this while a dialogue popup is open.
+const DUCK_LEVEL: f64 = 0.35;
+const DUCK_SPEED: f64 = 3.0;
+// Minus and Equal nudge the master volume; the settings menu cycles in bigger steps.
+const VOLUME_STEP: f64 = 0.1;
+const VOLUME_CYCLE_STEP: f64 = 0.2;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AudioBus {
+    Master,
+    Music,
+    Sfx,
+    Ui,
+}
+
+// Steps a bus down by VOLUME_CYCLE_STEP, wrapping from silent back to full.
+#[derive(Event, Clone)]
+pub struct CycleVolume(pub AudioBus);
+
+#[derive(Event, Clone)]
+pub struct ToggleMute;
+
+#[derive(Resource)]
+pub struct AudioMixer {
+    pub master: f64,
+    pub music: f64,
+    pub sfx: f64,
+    pub ui: f64,
+    pub muted: bool,
+    duck: f64,
+}
+
+impl FromWorld for AudioMixer {
+    fn from_world(world: &mut World) -> Self {
+        let settings = world.get_resource::<UserSettings>().cloned().unwrap_or_default();
+        Self {
+            master: settings.master_volume,
+            music: settings.music_volume,
+            sfx: settings.sfx_volume,
+            ui: settings.ui_volume,
+            muted: settings.muted,
+            duck: 1.0,
+        }
+    }
+}
+
+impl AudioMixer {
+    pub fn bus(&self, bus: AudioBus) -> f64 {
+        match bus {
+            AudioBus::Master => self.master,
+            AudioBus::Music => self.music,
+            AudioBus::Sfx => self.sfx,
+            AudioBus::Ui => self.ui,
+        }
+    }
+
+    fn bus_mut(&mut self, bus: AudioBus) -> &mut f64 {
+        match bus {
+            AudioBus::Master => &mut self.master,
+            AudioBus::Music => &mut self.music,
+            AudioBus::Sfx => &mut self.sfx,
+            AudioBus::Ui => &mut self.ui,
+        }
+    }
+
+    fn level(&self, mix: f64, bus: f64) -> f64 {
+        if self.muted {
+            0.0
+        } else {
+            mix * self.master * bus
+        }
+    }
+
+    pub fn music_level(&self) -> f64 {
+        self.level(MUSIC_MIX, self.music) * self.duck
+    }
+
+    pub fn sfx_level(&self) -> f64 {
+        self.level(SFX_MIX, self.sfx)
+    }
+
+    pub fn ui_level(&self) -> f64 {
+        self.level(UI_MIX, self.ui)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum SoundEffect {
+    Bump,
+    CatMeow,
+    Click,
+    Crunch,
+    DigitalAlarm,
+    DoorSlowOpen,
+    Drink,
+    LowHealth,
+    MenuIn,
+    MenuOut,
+    PhoneRing,
+    Steps,
+    Thunder,
+    WaterSplash,
+}
+
+impl SoundEffect {
+    pub fn path(&self) -> &'static str {
+        match self {
+            SoundEffect::Bump => "Bump.wav",
+            SoundEffect::CatMeow => "Cat_Meow.wav",
+            SoundEffect::Click => "Click.wav",
+            SoundEffect::Crunch => "Crunch.wav",
+            SoundEffect::DigitalAlarm => "Digital_Alarm.wav",
+            SoundEffect::DoorSlowOpen => "Door_Slow_Open.wav",
+            SoundEffect::Drink => "Drink.wav",
+            SoundEffect::LowHealth => "Low_Health.wav",
+            SoundEffect::MenuIn => "Menu_In.ogg",
+            SoundEffect::MenuOut => "Menu_Out.ogg",
+            SoundEffect::PhoneRing => "Phone_Ring.wav",
+            SoundEffect::Steps => "Steps.wav",
+            SoundEffect::Thunder => "Thunder.wav",
+            SoundEffect::WaterSplash => "Water_Splash.wav",
+        }
+    }
+
+    // Shortest gap between two plays, so spammed effects do not pile up.
+    pub fn cooldown(&self) -> f32 {
+        match self {
+            SoundEffect::Steps => 0.12,
+            SoundEffect::Bump => 0.25,
+            SoundEffect::LowHealth => 2.0,
+            SoundEffect::PhoneRing | SoundEffect::DigitalAlarm | SoundEffect::Thunder => 1.0,
+            _ => 0.05,
+        }
+    }
+
+    fn is_ui(&self) -> bool {
+        matches!(
+            self,
+            SoundEffect::MenuIn | SoundEffect::MenuOut | SoundEffect::Click
+        )
+    }
+}
+
+#[derive(Event, Clone)]
+pub struct PlaySfx {
+    pub effect: SoundEffect,
+    pub volume: f64,
+    // Playback rate is picked at random within 1.0 +/- this amount.
+    pub pitch_variation: f64,
+    // Loop the effect until this entity is despawned.
+    pub looped_on: Option<Entity>,
+}
+
+impl PlaySfx {
+    pub fn new(effect: SoundEffect) -> Self {
+        Self {
+            effect,
+            volume: 1.0,
+            pitch_variation: 0.0,
+            looped_on: None,
+        }
+    }
+
+    pub fn with_volume(mut self, volume: f64) -> Self {
+        self.volume = volume;
+        self
+    }
+
+    pub fn with_pitch_variation(mut self, variation: f64) -> Self {
+        self.pitch_variation = variation;
+        self
+    }
+
+    pub fn looped_on(mut self, owner: Entity) -> Self {
+        self.looped_on = Some(owner);
+        self
+    }
+}
 
-#[derive(Event, Resource)]
-pub struct ButtonPressTriggered;
+#[derive(Resource, Default)]
+struct SfxState {
+    last_played: HashMap<SoundEffect, f32>,
+    looping: HashMap<Entity, Handle<AudioInstance>>,
+}
 
 impl Plugin for GameAudioPlugin {
     fn build(&self, app: &mut App) {
-        
+
         app.add_audio_channel::<Background>();
         app.add_audio_channel::<ButtonPress>();
-        app.add_systems(OnEnter(GameState::Playing), play_bgm);
-        //app.add_systems(OnExit(GameState::Menu), play_button_press);
-        app.add_systems(Update, play_button_press);
+        app.add_audio_channel::<Effects>();
+        app.add_event::<PlaySfx>();
+        app.init_resource::<SfxState>();
+        app.init_resource::<AudioMixer>();
+        app.add_event::<CycleVolume>();
+        app.add_event::<ToggleMute>();
+        app.add_systems(Update, (play_footsteps, play_sfx, stop_orphaned_loops).chain());
+        app.add_systems(
+            Update,
+            (
+                mixer_hotkeys,
+                handle_mixer_events,
+                duck_music,
+                apply_mixer,
+                store_mixer_settings,
+            )
+                .chain(),
+        );
+    }
+}
+
+
+
+fn mixer_hotkeys(input: Res<GameInput>, mut mixer: ResMut<AudioMixer>) {
+    if input.just_pressed(GameAction::Mute) {
+        mixer.muted = !mixer.muted;
+    }
+    if input.just_pressed(GameAction::VolumeDown) {
+        mixer.master = (mixer.master - VOLUME_STEP).max(0.0);
+    }
+    if input.just_pressed(GameAction::VolumeUp) {
+        mixer.master = (mixer.master + VOLUME_STEP).min(1.0);
+    }
+}
+
+fn handle_mixer_events(
+    mut cycles: EventReader<CycleVolume>,
+    mut mutes: EventReader<ToggleMute>,
+    mut mixer: ResMut<AudioMixer>,
+) {
+    for CycleVolume(bus) in cycles.read() {
+        let level = mixer.bus_mut(*bus);
+        // Round first so repeated steps do not drift.
+        let stepped = ((*level - VOLUME_CYCLE_STEP) * 10.0).round() / 10.0;
+        *level = if stepped < 0.0 { 1.0 } else { stepped };
+    }
+    for _ in mutes.read() {
+        mixer.muted = !mixer.muted;
     }
 }
 
+fn duck_music(time: Res<Time>, popup_state: Option<Res<PopupState>>, mut mixer: ResMut<AudioMixer>) {
+    let target = match popup_state {
+        Some(state) if state.is_popup_active => DUCK_LEVEL,
+        _ => 1.0,
+    };
+    if mixer.duck == target {
+        return;
+    }
+
+    let step = DUCK_SPEED * time.delta_seconds_f64();
+    mixer.duck = if mixer.duck < target {
+        (mixer.duck + step).min(target)
+    } else {
+        (mixer.duck - step).max(target)
+    };
+}
+
+fn apply_mixer(
+    mixer: Res<AudioMixer>,
+    background: Res<AudioChannel<Background>>,
+    effects: Res<AudioChannel<Effects>>,
+    button_press: Res<AudioChannel<ButtonPress>>,
+) {
+    if !mixer.is_changed() {
+        return;
+    }
 
+    background.set_volume(mixer.music_level());
+    effects.set_volume(mixer.sfx_level());
+    button_press.set_volume(mixer.ui_level());
+}
 
-pub fn play_bgm(background: Res<AudioChannel<Background>>, asset_server: Res<AssetServer>, _audio: Res<Audio>) {
-    background.set_volume(0.05);
-    background
-    .play(asset_server.load("retroindiejosh_50s-bit.ogg")).looped();
+fn store_mixer_settings(mixer: Res<AudioMixer>, mut settings: ResMut<UserSettings>) {
+    if !mixer.is_changed() {
+        return;
+    }
 
+    let mut updated = settings.clone();
+    updated.master_volume = mixer.master;
+    updated.music_volume = mixer.music;
+    updated.sfx_volume = mixer.sfx;
+    updated.ui_volume = mixer.ui;
+    updated.muted = mixer.muted;
+    settings.set_if_neq(updated);
 }
 
+fn play_footsteps(
+    mut animation_events: EventReader<AnimationEvent>,
+    library: Res<SpritesheetLibrary>,
+    player: Option<Res<PosVar>>,
+    mut sfx: EventWriter<PlaySfx>,
+) {
+    let Some(player) = player else {
+        animation_events.clear();
+        return;
+    };
+
+    for event in animation_events.read() {
+        let AnimationEvent::MarkerHit { entity, marker_id, .. } = event else {
+            continue;
+        };
+        if *entity == player.id && library.is_marker_name(*marker_id, FOOTSTEP_MARKER) {
+            sfx.send(
+                PlaySfx::new(SoundEffect::Steps)
+                    .with_volume(0.6)
+                    .with_pitch_variation(0.08),
+            );
+        }
+    }
+}
 
-pub fn play_button_press(
-    mut button_press_event_writer: EventWriter<ButtonPressTriggered>,
+fn play_sfx(
+    mut requests: EventReader<PlaySfx>,
+    mut state: ResMut<SfxState>,
+    mut rng: Local<GameRng>,
+    time: Res<Time>,
+    effects: Res<AudioChannel<Effects>>,
     button_press: Res<AudioChannel<ButtonPress>>,
     asset_server: Res<AssetServer>,
-    _audio: Res<Audio>,
-    mut button_press_state: ResMut<ButtonPressState>
 ) {
-    if button_press_state.triggered {
-        button_press.set_volume(0.15);
-        button_press.play(asset_server.load("Menu_In.ogg"));
-        
-        button_press_event_writer.send(ButtonPressTriggered);
-        button_press_state.triggered = false;
+    let now = time.elapsed_seconds();
+
+    for request in requests.read() {
+        let effect = request.effect;
+        match request.looped_on {
+            // A loop plays for as long as its owner lives, so it is only ever skipped
+            // when that owner already has one; a cooldown would leave it silent.
+            Some(owner) => {
+                if state.looping.contains_key(&owner) {
+                    continue;
+                }
+            }
+            None => {
+                if let Some(last) = state.last_played.get(&effect) {
+                    if now - last < effect.cooldown() {
+                        continue;
+                    }
+                }
+                state.last_played.insert(effect, now);
+            }
+        }
+
+        let rate = 1.0 + (rng.next_f32() as f64 * 2.0 - 1.0) * request.pitch_variation;
+        let source = asset_server.load(effect.path());
+        let mut command = if effect.is_ui() {
+            button_press.play(source)
+        } else {
+            effects.play(source)
+        };
+        command.with_volume(request.volume).with_playback_rate(rate);
+
+        if let Some(owner) = request.looped_on {
+            command.looped();
+            let handle = command.handle();
+            state.looping.insert(owner, handle);
+        }
+    }
+}
+
+fn stop_orphaned_loops(
+    mut state: ResMut<SfxState>,
+    owners: Query<Entity>,
+    mut audio_instances: ResMut<Assets<AudioInstance>>,
+) {
+    if state.looping.is_empty() {
+        return;
     }
 
+    state.looping.retain(|owner, handle| {
+        if owners.contains(*owner) {
+            return true;
+        }
+        if let Some(instance) = audio_instances.get_mut(handle) {
+            instance.stop(AudioTween::default());
+        }
+        false
+    });
 }
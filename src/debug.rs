@@ -0,0 +1,232 @@
+use bevy::diagnostic::{DiagnosticsStore, FrameTimeDiagnosticsPlugin};
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+use bevy_ecs_tiled::prelude::{TiledMap, TiledMapHandle};
+use tiled::{LayerType, ObjectShape};
+
+use crate::input::{GameAction, GameInput};
+use crate::level::{tiled_to_world, world_to_tile, LevelGrid, TILE_SIZE};
+use crate::text::GameTime;
+use crate::{GameState, PosVar};
+
+// F3 toggles gizmos for the collision data and a readout of where everything is.
+pub struct DebugOverlayPlugin;
+
+const GRID_COLOR: Color = Color::srgba(1.0, 1.0, 1.0, 0.15);
+const BLOCKED_COLOR: Color = Color::srgba(1.0, 0.2, 0.2, 0.35);
+const OBSTACLE_COLOR: Color = Color::srgb(1.0, 0.2, 0.2);
+const TILE_COLLISION_COLOR: Color = Color::srgb(1.0, 0.6, 0.1);
+const PLAYER_COLOR: Color = Color::srgb(0.2, 1.0, 0.4);
+
+#[derive(Resource, Default)]
+struct DebugOverlay {
+    visible: bool,
+}
+
+#[derive(Component)]
+struct DebugText;
+
+impl Plugin for DebugOverlayPlugin {
+    fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<FrameTimeDiagnosticsPlugin>() {
+            app.add_plugins(FrameTimeDiagnosticsPlugin);
+        }
+        app.init_resource::<DebugOverlay>();
+        app.add_systems(Update, toggle_overlay);
+        app.add_systems(
+            Update,
+            (draw_level_grid, draw_obstacles, draw_tile_collisions, update_debug_text)
+                .run_if(|overlay: Res<DebugOverlay>| overlay.visible),
+        );
+    }
+}
+
+fn toggle_overlay(
+    mut commands: Commands,
+    input: Res<GameInput>,
+    mut overlay: ResMut<DebugOverlay>,
+    texts: Query<Entity, With<DebugText>>,
+) {
+    if !input.just_pressed(GameAction::DebugOverlay) {
+        return;
+    }
+
+    overlay.visible = !overlay.visible;
+    if !overlay.visible {
+        for entity in &texts {
+            commands.entity(entity).despawn_recursive();
+        }
+        return;
+    }
+
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font_size: 16.0,
+                color: Color::WHITE,
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            left: Val::Px(10.0),
+            top: Val::Px(50.0),
+            padding: UiRect::all(Val::Px(6.0)),
+            ..default()
+        })
+        .with_background_color(Color::BLACK.with_alpha(0.6)),
+        // Above the HUD and dialogue, below transitions.
+        ZIndex::Global(i32::MAX - 1),
+        DebugText,
+    ));
+}
+
+// Cell edges sit half a tile off the tile centers.
+fn draw_level_grid(mut gizmos: Gizmos, grid: Res<LevelGrid>, player: Option<Res<PosVar>>) {
+    if !grid.is_loaded() {
+        return;
+    }
+
+    let edge = |cells: i32| (cells as f32 - 0.5) * TILE_SIZE;
+    for x in 0..=grid.width {
+        gizmos.line_2d(Vec2::new(edge(x), edge(0)), Vec2::new(edge(x), edge(grid.height)), GRID_COLOR);
+    }
+    for y in 0..=grid.height {
+        gizmos.line_2d(Vec2::new(edge(0), edge(y)), Vec2::new(edge(grid.width), edge(y)), GRID_COLOR);
+    }
+
+    let cell = Vec2::splat(TILE_SIZE);
+    for y in 0..grid.height {
+        for x in 0..grid.width {
+            let tile = IVec2::new(x, y);
+            if !grid.is_walkable(tile) {
+                gizmos.rect_2d(tile.as_vec2() * TILE_SIZE, Rot2::IDENTITY, cell * 0.8, BLOCKED_COLOR);
+            }
+        }
+    }
+
+    if let Some(player) = player {
+        let tile = world_to_tile(player.pos_vec);
+        gizmos.rect_2d(tile.as_vec2() * TILE_SIZE, Rot2::IDENTITY, cell, PLAYER_COLOR);
+    }
+}
+
+fn draw_obstacles(mut gizmos: Gizmos, grid: Res<LevelGrid>) {
+    for obstacle in &grid.obstacles {
+        gizmos.rect_2d(obstacle.center(), Rot2::IDENTITY, obstacle.size(), OBSTACLE_COLOR);
+    }
+}
+
+// The collision shapes drawn on tiles in the tileset editor, wherever those tiles are placed.
+fn draw_tile_collisions(mut gizmos: Gizmos, maps: Res<Assets<TiledMap>>, handles: Query<&TiledMapHandle>) {
+    for handle in &handles {
+        let Some(tiled_map) = maps.get(&handle.0) else {
+            continue;
+        };
+        let map = &tiled_map.map;
+        let map_height_px = (map.height * map.tile_height) as f32;
+
+        for layer in map.layers() {
+            let LayerType::Tiles(tiles) = layer.layer_type() else {
+                continue;
+            };
+            for y in 0..map.height as i32 {
+                for x in 0..map.width as i32 {
+                    let Some(collision) = tiles
+                        .get_tile(x, y)
+                        .and_then(|layer_tile| layer_tile.get_tile())
+                        .and_then(|tile| tile.collision.clone())
+                    else {
+                        continue;
+                    };
+                    let origin = Vec2::new(
+                        (x as u32 * map.tile_width) as f32,
+                        (y as u32 * map.tile_height) as f32,
+                    );
+                    for object in collision.object_data() {
+                        let at = |dx: f32, dy: f32| {
+                            tiled_to_world(map_height_px, origin.x + object.x + dx, origin.y + object.y + dy)
+                        };
+                        match &object.shape {
+                            ObjectShape::Rect { width, height } => {
+                                let rect = Rect::from_corners(at(0.0, 0.0), at(*width, *height));
+                                gizmos.rect_2d(rect.center(), Rot2::IDENTITY, rect.size(), TILE_COLLISION_COLOR);
+                            }
+                            ObjectShape::Polygon { points } => {
+                                let mut outline: Vec<Vec2> =
+                                    points.iter().map(|(px, py)| at(*px, *py)).collect();
+                                outline.extend(outline.first().copied());
+                                gizmos.linestrip_2d(outline, TILE_COLLISION_COLOR);
+                            }
+                            ObjectShape::Polyline { points } => {
+                                let line = points.iter().map(|(px, py)| at(*px, *py));
+                                gizmos.linestrip_2d(line, TILE_COLLISION_COLOR);
+                            }
+                            ObjectShape::Ellipse { width, height } => {
+                                let center = at(width / 2.0, height / 2.0);
+                                gizmos.ellipse_2d(
+                                    center,
+                                    0.0,
+                                    Vec2::new(width / 2.0, height / 2.0),
+                                    TILE_COLLISION_COLOR,
+                                );
+                            }
+                            _ => {
+                                gizmos.circle_2d(at(0.0, 0.0), 2.0, TILE_COLLISION_COLOR);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn update_debug_text(
+    mut texts: Query<&mut Text, With<DebugText>>,
+    state: Res<State<GameState>>,
+    game_time: Option<Res<GameTime>>,
+    player: Option<Res<PosVar>>,
+    diagnostics: Res<DiagnosticsStore>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    cameras: Query<(&Camera, &GlobalTransform)>,
+) {
+    let fps = diagnostics
+        .get(&FrameTimeDiagnosticsPlugin::FPS)
+        .and_then(|fps| fps.smoothed())
+        .map(|fps| format!("{:.0}", fps))
+        .unwrap_or_else(|| "-".to_string());
+    let player = player
+        .map(|player| {
+            let tile = world_to_tile(player.pos_vec);
+            format!("({}, {}) at {:.0}, {:.0}", tile.x, tile.y, player.pos_vec.x, player.pos_vec.y)
+        })
+        .unwrap_or_else(|| "-".to_string());
+    let clock = game_time
+        .map(|time| format!("{}:{:02} ({} min in)", time.hours, time.minutes, time.elapsed_minutes()))
+        .unwrap_or_else(|| "-".to_string());
+    let cursor = windows
+        .get_single()
+        .ok()
+        .and_then(|window| window.cursor_position())
+        .zip(cameras.iter().find(|(camera, _)| camera.is_active))
+        .and_then(|(cursor, (camera, transform))| camera.viewport_to_world_2d(transform, cursor))
+        .map(|world| {
+            let tile = world_to_tile(world.extend(0.0));
+            format!("{:.0}, {:.0} tile ({}, {})", world.x, world.y, tile.x, tile.y)
+        })
+        .unwrap_or_else(|| "-".to_string());
+
+    let readout = format!(
+        "FPS {}\nState {:?}\nTime {}\nPlayer tile {}\nMouse {}",
+        fps,
+        state.get(),
+        clock,
+        player,
+        cursor
+    );
+    for mut text in &mut texts {
+        text.sections[0].value = readout.clone();
+    }
+}
@@ -0,0 +1,247 @@
+use bevy::input::InputSystem;
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts, EguiPlugin};
+use bevy_tweening::Animator;
+
+use crate::input::{GameAction, GameInput, GameInputSet};
+use crate::level::{tile_to_world, world_to_tile};
+use crate::mess::{Mess, MessLevel};
+use crate::night::{CurrentLevel, LEVELS};
+use crate::text::{GameTime, PopupQueue};
+use crate::transition::TransitionTo;
+use crate::{GameState, MapInfo, PosVar};
+
+// Only built with `--features dev`. F1 opens an inspector for the main resources
+// and a console for jumping around a night.
+pub struct DevToolsPlugin;
+
+// Lines of console output kept on screen.
+const CONSOLE_HISTORY: usize = 50;
+
+const HELP: &str = "set-time <h:mm> | teleport <x> <y> | complete-all-tasks | load-level <n> | help";
+
+#[derive(Resource, Default)]
+struct DevTools {
+    open: bool,
+    input: String,
+    history: Vec<String>,
+}
+
+impl DevTools {
+    fn log(&mut self, line: impl Into<String>) {
+        self.history.push(line.into());
+        if self.history.len() > CONSOLE_HISTORY {
+            self.history.remove(0);
+        }
+    }
+}
+
+enum ConsoleCommand {
+    SetTime { hours: u32, minutes: u32 },
+    // Tile coordinates.
+    Teleport(IVec2),
+    CompleteAllTasks,
+    // 1-based, as shown in the HUD.
+    LoadLevel(usize),
+    Help,
+}
+
+impl ConsoleCommand {
+    fn parse(line: &str) -> Result<Self, String> {
+        let mut words = line.split_whitespace();
+        let Some(name) = words.next() else {
+            return Err("Empty command".to_string());
+        };
+        let args: Vec<&str> = words.collect();
+        let number = |text: &str| text.parse::<i32>().map_err(|_| format!("'{}' is not a number", text));
+
+        match (name, args.as_slice()) {
+            ("set-time", [time]) => {
+                let (hours, minutes) = time
+                    .split_once(':')
+                    .and_then(|(hours, minutes)| Some((hours.parse().ok()?, minutes.parse().ok()?)))
+                    .filter(|(hours, minutes): &(u32, u32)| (1..=12).contains(hours) && *minutes < 60)
+                    .ok_or_else(|| format!("'{}' is not a time like 7:30", time))?;
+                Ok(ConsoleCommand::SetTime { hours: hours % 12, minutes })
+            }
+            ("teleport", [x, y]) => Ok(ConsoleCommand::Teleport(IVec2::new(number(x)?, number(y)?))),
+            ("complete-all-tasks", []) => Ok(ConsoleCommand::CompleteAllTasks),
+            ("load-level", [level]) => {
+                let level = number(level)?;
+                if level < 1 || level as usize > LEVELS.len() {
+                    return Err(format!("There are levels 1 to {}", LEVELS.len()));
+                }
+                Ok(ConsoleCommand::LoadLevel(level as usize))
+            }
+            ("help", []) => Ok(ConsoleCommand::Help),
+            _ => Err(format!("Unknown command '{}'. {}", line.trim(), HELP)),
+        }
+    }
+
+    fn run(self, world: &mut World) -> String {
+        let in_night = matches!(
+            world.resource::<State<GameState>>().get(),
+            GameState::Playing | GameState::Paused
+        );
+        match self {
+            ConsoleCommand::Help => HELP.to_string(),
+            ConsoleCommand::LoadLevel(level) => {
+                world.resource_mut::<CurrentLevel>().0 = level - 1;
+                world.send_event(TransitionTo::new(GameState::Loading));
+                format!("Loading level {}", level)
+            }
+            _ if !in_night => "Start a night first".to_string(),
+            ConsoleCommand::SetTime { hours, minutes } => {
+                let mut game_time = world.resource_mut::<GameTime>();
+                game_time.hours = hours;
+                game_time.minutes = minutes;
+                format!("Time set to {}:{:02}", if hours == 0 { 12 } else { hours }, minutes)
+            }
+            ConsoleCommand::Teleport(tile) => {
+                let mut player = world.resource_mut::<PosVar>();
+                let target = tile_to_world(tile, player.pos_vec.z);
+                player.pos_vec = target;
+                player.in_anim = false;
+                let id = player.id;
+                // Drop any step in flight so it cannot pull Cliff back.
+                if let Some(mut entity) = world.get_entity_mut(id) {
+                    entity.remove::<Animator<Transform>>();
+                    if let Some(mut transform) = entity.get_mut::<Transform>() {
+                        transform.translation = target;
+                    }
+                }
+                format!("Teleported to ({}, {})", tile.x, tile.y)
+            }
+            ConsoleCommand::CompleteAllTasks => {
+                let messes: Vec<Entity> = world
+                    .query_filtered::<Entity, With<Mess>>()
+                    .iter(world)
+                    .collect();
+                for entity in &messes {
+                    world.entity_mut(*entity).despawn_recursive();
+                }
+                let required = world.resource::<CurrentLevel>().config().tasks_required;
+                let mut mess_level = world.resource_mut::<MessLevel>();
+                mess_level.cleaned = (mess_level.cleaned + messes.len() as u32).max(required);
+                format!("Cleaned {} messes, {} tasks done", messes.len(), mess_level.cleaned)
+            }
+        }
+    }
+}
+
+impl Plugin for DevToolsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(EguiPlugin);
+        app.init_resource::<DevTools>();
+        app.add_systems(
+            PreUpdate,
+            hold_input_while_typing.after(InputSystem).before(GameInputSet),
+        );
+        app.add_systems(Update, (toggle_dev_tools, dev_tools_window).chain());
+    }
+}
+
+fn toggle_dev_tools(input: Res<GameInput>, mut tools: ResMut<DevTools>) {
+    if input.just_pressed(GameAction::DevTools) {
+        tools.open = !tools.open;
+    }
+}
+
+// Typing in the console should not walk Cliff around or open menus.
+fn hold_input_while_typing(mut contexts: EguiContexts, mut keys: ResMut<ButtonInput<KeyCode>>) {
+    if contexts.try_ctx_mut().is_some_and(|ctx| ctx.wants_keyboard_input()) {
+        keys.reset_all();
+    }
+}
+
+fn dev_tools_window(
+    mut commands: Commands,
+    mut contexts: EguiContexts,
+    mut tools: ResMut<DevTools>,
+    mut game_time: Option<ResMut<GameTime>>,
+    mut player: Option<ResMut<PosVar>>,
+    mut map_info: ResMut<MapInfo>,
+    mut popup_queue: Option<ResMut<PopupQueue>>,
+) {
+    if !tools.open {
+        return;
+    }
+
+    let mut submitted = None;
+    egui::Window::new("Dev tools").show(contexts.ctx_mut(), |ui| {
+        ui.collapsing("GameTime", |ui| match game_time.as_deref_mut() {
+            Some(game_time) => {
+                ui.horizontal(|ui| {
+                    ui.add(egui::DragValue::new(&mut game_time.hours).range(0..=11).prefix("h "));
+                    ui.add(egui::DragValue::new(&mut game_time.minutes).range(0..=59).prefix("m "));
+                });
+                ui.label(format!("{} minutes into the shift", game_time.elapsed_minutes()));
+            }
+            None => {
+                ui.label("No night running");
+            }
+        });
+
+        ui.collapsing("PosVar", |ui| match player.as_deref_mut() {
+            Some(player) => {
+                let tile = world_to_tile(player.pos_vec);
+                ui.label(format!("Tile ({}, {})", tile.x, tile.y));
+                ui.horizontal(|ui| {
+                    ui.add(egui::DragValue::new(&mut player.pos_vec.x).prefix("x "));
+                    ui.add(egui::DragValue::new(&mut player.pos_vec.y).prefix("y "));
+                });
+                ui.checkbox(&mut player.in_anim, "Mid-step");
+                ui.label(format!("Facing {:?}", player.last_direction));
+            }
+            None => {
+                ui.label("No player");
+            }
+        });
+
+        ui.collapsing("MapInfo", |ui| {
+            ui.add(egui::DragValue::new(&mut map_info.map_width).prefix("width "));
+            ui.add(egui::DragValue::new(&mut map_info.map_height).prefix("height "));
+        });
+
+        ui.collapsing("PopupQueue", |ui| match popup_queue.as_deref_mut() {
+            Some(queue) => {
+                ui.label(format!("{} waiting", queue.previews().count()));
+                for text in queue.previews() {
+                    ui.label(text);
+                }
+                if ui.button("Clear").clicked() {
+                    queue.clear();
+                }
+            }
+            None => {
+                ui.label("No night running");
+            }
+        });
+
+        ui.separator();
+        egui::ScrollArea::vertical().max_height(160.0).stick_to_bottom(true).show(ui, |ui| {
+            for line in &tools.history {
+                ui.monospace(line);
+            }
+        });
+        let response = ui.text_edit_singleline(&mut tools.input);
+        if response.lost_focus() && ui.input(|input| input.key_pressed(egui::Key::Enter)) {
+            submitted = Some(std::mem::take(&mut tools.input));
+            response.request_focus();
+        }
+    });
+
+    let Some(line) = submitted else {
+        return;
+    };
+    tools.log(format!("> {}", line));
+    match ConsoleCommand::parse(&line) {
+        Ok(command) => {
+            commands.add(move |world: &mut World| {
+                let output = command.run(world);
+                world.resource_mut::<DevTools>().log(output);
+            });
+        }
+        Err(error) => tools.log(error),
+    }
+}
@@ -0,0 +1,423 @@
+use bevy::prelude::*;
+
+use crate::audio::{PlaySfx, SoundEffect};
+use crate::input::{GameAction, GameInput, GameInputSet, PointerInputSet};
+use crate::locale::Locale;
+use crate::settings::UserSettings;
+use crate::text::{PopupQueue, PopupState};
+use crate::InNight;
+
+pub struct DialoguePlugin;
+
+// Blip on every other letter; one per letter turns into a buzz at normal speeds.
+const BLIP_EVERY: usize = 2;
+const BLIP_PITCH_VARIATION: f64 = 0.12;
+
+const PANEL_HEIGHT: f32 = 140.;
+// Portraits are sprite sheet frames drawn at twice their pixel size.
+const PORTRAIT_SIZE: Vec2 = Vec2::new(64., 96.);
+const NAME_COLOR: Color = Color::srgb(0.95, 0.8, 0.35);
+// Cliff's front idle frame in the janitor sprite sheet.
+const CLIFF_PORTRAIT: usize = 0;
+const CONTINUE_BLINK_SECONDS: f32 = 0.5;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Speaker {
+    Narrator,
+    Cliff,
+}
+
+impl Speaker {
+    fn name_key(&self) -> Option<&'static str> {
+        match self {
+            Speaker::Narrator => None,
+            Speaker::Cliff => Some("speaker-cliff"),
+        }
+    }
+
+    fn portrait(&self) -> Option<usize> {
+        match self {
+            Speaker::Narrator => None,
+            Speaker::Cliff => Some(CLIFF_PORTRAIT),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct DialogueLine {
+    pub speaker: Speaker,
+    pub text: String,
+}
+
+impl DialogueLine {
+    pub fn new(speaker: Speaker, text: impl Into<String>) -> Self {
+        Self {
+            speaker,
+            text: text.into(),
+        }
+    }
+
+    pub fn narrator(text: impl Into<String>) -> Self {
+        Self::new(Speaker::Narrator, text)
+    }
+}
+
+#[derive(Resource)]
+pub struct TypewriterSettings {
+    pub chars_per_second: f32,
+    pub blip: Option<SoundEffect>,
+}
+
+impl FromWorld for TypewriterSettings {
+    fn from_world(world: &mut World) -> Self {
+        let settings = world.get_resource::<UserSettings>().cloned().unwrap_or_default();
+        Self {
+            chars_per_second: settings.text_speed,
+            blip: Some(SoundEffect::Click),
+        }
+    }
+}
+
+// Opens the dialogue panel on a line, replacing whatever it showed before.
+#[derive(Event, Clone)]
+pub struct ShowDialogue(pub DialogueLine);
+
+// Finishes the line being typed, or closes it once it is fully shown.
+#[derive(Event, Clone)]
+pub struct AdvanceDialogue;
+
+struct ActiveLine {
+    root: Entity,
+    chars: Vec<char>,
+    revealed: usize,
+    // How much of the line the text entity currently shows.
+    shown: usize,
+    elapsed: f32,
+}
+
+impl ActiveLine {
+    fn is_revealing(&self) -> bool {
+        self.revealed < self.chars.len()
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct Dialogue {
+    line: Option<ActiveLine>,
+}
+
+#[derive(Resource)]
+struct Portraits {
+    texture: Handle<Image>,
+    layout: Handle<TextureAtlasLayout>,
+}
+
+impl FromWorld for Portraits {
+    fn from_world(world: &mut World) -> Self {
+        let texture = world.resource::<AssetServer>().load("janitor_spritesheet.png");
+        let layout = world
+            .resource_mut::<Assets<TextureAtlasLayout>>()
+            .add(TextureAtlasLayout::from_grid(UVec2::new(32, 48), 3, 4, None, None));
+        Self { texture, layout }
+    }
+}
+
+#[derive(Component)]
+struct DialogueText;
+
+#[derive(Component)]
+struct DialogueAdvanceButton;
+
+#[derive(Component)]
+struct ContinueIndicator;
+
+impl Plugin for DialoguePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TypewriterSettings>();
+        app.init_resource::<Dialogue>();
+        app.init_resource::<Portraits>();
+        app.add_event::<ShowDialogue>();
+        app.add_event::<AdvanceDialogue>();
+        app.add_systems(PreUpdate, advance_button.in_set(PointerInputSet));
+        app.add_systems(PreUpdate, dialogue_input.after(GameInputSet));
+        app.add_systems(OnExit(InNight), close_dialogue);
+        app.add_systems(
+            Update,
+            (
+                show_dialogue,
+                advance_dialogue,
+                reveal_dialogue_text,
+                blink_continue_indicator,
+            )
+                .chain(),
+        );
+    }
+}
+
+fn show_dialogue(
+    mut commands: Commands,
+    mut requests: EventReader<ShowDialogue>,
+    mut dialogue: ResMut<Dialogue>,
+    portraits: Res<Portraits>,
+    locale: Res<Locale>,
+) {
+    for ShowDialogue(line) in requests.read() {
+        close_line(&mut commands, &mut dialogue);
+
+        // The whole panel is the advance button.
+        let root = commands
+            .spawn((
+                ButtonBundle {
+                    style: Style {
+                        position_type: PositionType::Absolute,
+                        left: Val::Percent(5.),
+                        right: Val::Percent(5.),
+                        bottom: Val::Px(20.),
+                        min_height: Val::Px(PANEL_HEIGHT),
+                        padding: UiRect::all(Val::Px(16.)),
+                        column_gap: Val::Px(16.),
+                        align_items: AlignItems::Center,
+                        ..default()
+                    },
+                    background_color: Color::BLACK.with_alpha(0.9).into(),
+                    z_index: ZIndex::Global(100),
+                    ..default()
+                },
+                DialogueAdvanceButton,
+            ))
+            .with_children(|parent| {
+                if let Some(index) = line.speaker.portrait() {
+                    parent.spawn((
+                        ImageBundle {
+                            style: Style {
+                                width: Val::Px(PORTRAIT_SIZE.x),
+                                height: Val::Px(PORTRAIT_SIZE.y),
+                                flex_shrink: 0.,
+                                ..default()
+                            },
+                            image: UiImage::new(portraits.texture.clone()),
+                            ..default()
+                        },
+                        TextureAtlas {
+                            layout: portraits.layout.clone(),
+                            index,
+                        },
+                    ));
+                }
+
+                parent
+                    .spawn(NodeBundle {
+                        style: Style {
+                            flex_direction: FlexDirection::Column,
+                            flex_grow: 1.,
+                            row_gap: Val::Px(8.),
+                            ..default()
+                        },
+                        ..default()
+                    })
+                    .with_children(|parent| {
+                        if let Some(key) = line.speaker.name_key() {
+                            parent.spawn(TextBundle::from_section(
+                                locale.get(key),
+                                TextStyle {
+                                    font_size: 22.,
+                                    color: NAME_COLOR,
+                                    ..default()
+                                },
+                            ));
+                        }
+                        parent.spawn((typed_text(&line.text), DialogueText));
+                    });
+
+                parent.spawn((continue_indicator(), ContinueIndicator));
+            })
+            .id();
+
+        dialogue.line = Some(ActiveLine {
+            root,
+            chars: line.text.chars().collect(),
+            revealed: 0,
+            shown: 0,
+            elapsed: 0.,
+        });
+    }
+}
+
+// Whatever was being said, or still waiting to be, is dropped along with the night.
+fn close_dialogue(
+    mut commands: Commands,
+    mut dialogue: ResMut<Dialogue>,
+    mut popup_state: ResMut<PopupState>,
+    mut popup_queue: ResMut<PopupQueue>,
+) {
+    close_line(&mut commands, &mut dialogue);
+    popup_state.is_popup_active = false;
+    popup_queue.clear();
+}
+
+fn close_line(commands: &mut Commands, dialogue: &mut Dialogue) {
+    if let Some(line) = dialogue.line.take() {
+        commands.entity(line.root).despawn_recursive();
+    }
+}
+
+// The unrevealed rest of the line is laid out but transparent, so the box does
+// not grow or reflow while the text types out.
+fn typed_text(text: &str) -> TextBundle {
+    TextBundle::from_sections([
+        TextSection::new(
+            "",
+            TextStyle {
+                font_size: 24.,
+                color: Color::WHITE,
+                ..default()
+            },
+        ),
+        TextSection::new(
+            text,
+            TextStyle {
+                font_size: 24.,
+                color: Color::NONE,
+                ..default()
+            },
+        ),
+    ])
+}
+
+fn continue_indicator() -> TextBundle {
+    TextBundle {
+        text: Text::from_section(
+            "->",
+            TextStyle {
+                font_size: 18.,
+                color: Color::WHITE,
+                ..default()
+            },
+        ),
+        style: Style {
+            position_type: PositionType::Absolute,
+            right: Val::Px(12.),
+            bottom: Val::Px(8.),
+            ..default()
+        },
+        visibility: Visibility::Hidden,
+        ..default()
+    }
+}
+
+// Dialogue owns Interact and Confirm while a line is up, so those presses
+// never reach gameplay.
+fn dialogue_input(
+    popup_state: Option<Res<PopupState>>,
+    mut input: ResMut<GameInput>,
+    mut advance: EventWriter<AdvanceDialogue>,
+) {
+    if !popup_state.is_some_and(|state| state.is_popup_active) {
+        return;
+    }
+
+    let mut pressed = false;
+    for action in [GameAction::Interact, GameAction::Confirm] {
+        pressed |= input.clear_just_pressed(action);
+    }
+    if pressed {
+        advance.send(AdvanceDialogue);
+    }
+}
+
+// Clicking the panel is a Confirm press, so replays see it like any other.
+fn advance_button(
+    buttons: Query<&Interaction, (Changed<Interaction>, With<DialogueAdvanceButton>)>,
+    mut input: ResMut<GameInput>,
+) {
+    for interaction in &buttons {
+        if *interaction == Interaction::Pressed {
+            input.press(GameAction::Confirm);
+        }
+    }
+}
+
+fn advance_dialogue(
+    mut commands: Commands,
+    mut requests: EventReader<AdvanceDialogue>,
+    mut dialogue: ResMut<Dialogue>,
+    mut popup_state: ResMut<PopupState>,
+    mut sfx: EventWriter<PlaySfx>,
+) {
+    // Several presses in one frame still only move things along by one step.
+    if requests.read().count() == 0 {
+        return;
+    }
+    let Some(line) = dialogue.line.as_mut() else {
+        return;
+    };
+
+    if line.is_revealing() {
+        line.revealed = line.chars.len();
+        return;
+    }
+
+    close_line(&mut commands, &mut dialogue);
+    popup_state.is_popup_active = false;
+    sfx.send(PlaySfx::new(SoundEffect::MenuIn));
+}
+
+fn reveal_dialogue_text(
+    time: Res<Time>,
+    settings: Res<TypewriterSettings>,
+    mut dialogue: ResMut<Dialogue>,
+    mut texts: Query<&mut Text, With<DialogueText>>,
+    mut sfx: EventWriter<PlaySfx>,
+) {
+    let Some(line) = dialogue.line.as_mut() else {
+        return;
+    };
+
+    let before = line.revealed;
+    if line.is_revealing() {
+        line.elapsed += time.delta_seconds();
+        let typed = (line.elapsed * settings.chars_per_second.max(1.)) as usize;
+        line.revealed = typed.min(line.chars.len());
+    }
+
+    // Skipping to the end of the line is silent.
+    if let Some(blip) = settings.blip {
+        let blips = line.chars[before..line.revealed]
+            .iter()
+            .enumerate()
+            .any(|(offset, c)| !c.is_whitespace() && (before + offset) % BLIP_EVERY == 0);
+        if blips {
+            sfx.send(PlaySfx::new(blip).with_pitch_variation(BLIP_PITCH_VARIATION));
+        }
+    }
+
+    if line.shown == line.revealed {
+        return;
+    }
+    line.shown = line.revealed;
+    for mut text in &mut texts {
+        text.sections[0].value = line.chars[..line.revealed].iter().collect();
+        text.sections[1].value = line.chars[line.revealed..].iter().collect();
+    }
+}
+
+// The continue arrow only shows once the whole line is out, and blinks.
+fn blink_continue_indicator(
+    time: Res<Time>,
+    dialogue: Res<Dialogue>,
+    mut indicators: Query<&mut Visibility, With<ContinueIndicator>>,
+) {
+    let Some(line) = dialogue.line.as_ref() else {
+        return;
+    };
+
+    let blink_on = ((time.elapsed_seconds() / CONTINUE_BLINK_SECONDS) as u32).is_multiple_of(2);
+    let visibility = if !line.is_revealing() && blink_on {
+        Visibility::Inherited
+    } else {
+        Visibility::Hidden
+    };
+    for mut indicator in &mut indicators {
+        indicator.set_if_neq(visibility);
+    }
+}
@@ -0,0 +1,248 @@
+use bevy::prelude::*;
+use std::time::Duration;
+
+use crate::audio::{PlaySfx, SoundEffect};
+use crate::input::{GameAction, GameInput, InteractSet};
+use crate::level::{LevelGrid, LevelGridBuilt};
+use crate::map_objects::{MapObject, MapObjectSet, RegisterMapObject};
+use crate::text::no_modal_open;
+use crate::{GameState, InNight, PlayerStepped, PosVar};
+
+pub struct EnergyPlugin;
+
+pub const MAX_ENERGY: f32 = 100.0;
+
+// Each grid step costs a little; cleaning and other work spend more.
+const STEP_COST: f32 = 0.4;
+const LOW_ENERGY: f32 = 25.0;
+const REFRESHMENT_RANGE: f32 = 30.0;
+
+// A rested Cliff takes 250ms per step, an exhausted one twice as long.
+pub const RESTED_STEP: Duration = Duration::from_millis(250);
+const EXHAUSTED_STEP: Duration = Duration::from_millis(500);
+
+#[derive(Resource)]
+pub struct Energy {
+    pub current: f32,
+    pub max: f32,
+    low_warned: bool,
+}
+
+impl Default for Energy {
+    fn default() -> Self {
+        Self {
+            current: MAX_ENERGY,
+            max: MAX_ENERGY,
+            low_warned: false,
+        }
+    }
+}
+
+impl Energy {
+    pub fn spend(&mut self, amount: f32) {
+        self.current = (self.current - amount).max(0.0);
+    }
+
+    pub fn restore(&mut self, amount: f32) {
+        self.current = (self.current + amount).min(self.max);
+    }
+
+    pub fn fraction(&self) -> f32 {
+        self.current / self.max
+    }
+
+    pub fn is_low(&self) -> bool {
+        self.current <= LOW_ENERGY
+    }
+
+    // Steps only slow down once Cliff is low on energy.
+    pub fn step_duration(&self) -> Duration {
+        let tiredness = (1.0 - self.current / LOW_ENERGY).clamp(0.0, 1.0);
+        RESTED_STEP + (EXHAUSTED_STEP - RESTED_STEP).mul_f32(tiredness)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RefreshmentKind {
+    CoffeeMachine,
+    WaterCooler,
+}
+
+impl RefreshmentKind {
+    // Coffee does more for Cliff than water, unless the map says otherwise.
+    fn default_restores(&self) -> f32 {
+        match self {
+            RefreshmentKind::CoffeeMachine => 40.0,
+            RefreshmentKind::WaterCooler => 20.0,
+        }
+    }
+
+    fn color(&self) -> Color {
+        match self {
+            RefreshmentKind::CoffeeMachine => Color::srgb(0.35, 0.2, 0.1),
+            RefreshmentKind::WaterCooler => Color::srgb(0.55, 0.8, 0.95),
+        }
+    }
+}
+
+// A "CoffeeMachine" or "WaterCooler" object in the map. Cliff drinks from the tiles
+// next to it; the station's own tile is blocked.
+#[derive(Component)]
+pub struct Refreshment {
+    pub kind: RefreshmentKind,
+    pub restores: f32,
+}
+
+#[derive(Component)]
+struct EnergyBarFill;
+
+impl Plugin for EnergyPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Energy>();
+        for (class, kind) in [
+            ("CoffeeMachine", RefreshmentKind::CoffeeMachine),
+            ("WaterCooler", RefreshmentKind::WaterCooler),
+        ] {
+            app.register_map_object(class, move |properties| {
+                Ok((
+                    Refreshment {
+                        kind,
+                        restores: properties.get_or("restores", kind.default_restores())?,
+                    },
+                    Sprite {
+                        color: kind.color(),
+                        custom_size: Some(Vec2::new(12., 18.)),
+                        ..default()
+                    },
+                    Handle::<Image>::default(),
+                ))
+            });
+        }
+        app.add_systems(OnEnter(InNight), (reset_energy, spawn_energy_bar));
+        app.add_systems(
+            Update,
+            block_refreshments.after(MapObjectSet).run_if(in_state(InNight)),
+        );
+        app.add_systems(
+            Update,
+            (
+                drain_energy_on_step,
+                use_refreshment.run_if(no_modal_open).in_set(InteractSet::Refreshment),
+                warn_low_energy,
+                update_energy_bar,
+            )
+                .chain()
+                .run_if(in_state(GameState::Playing)),
+        );
+    }
+}
+
+fn reset_energy(mut commands: Commands) {
+    commands.insert_resource(Energy::default());
+}
+
+// Cliff walks up to a station rather than into it, so block its tile again whenever the
+// grid is rebuilt or new stations arrive.
+fn block_refreshments(
+    mut built: EventReader<LevelGridBuilt>,
+    mut grid: ResMut<LevelGrid>,
+    stations: Query<&MapObject, With<Refreshment>>,
+    added: Query<(), Added<Refreshment>>,
+) {
+    if built.read().count() == 0 && added.is_empty() {
+        return;
+    }
+    if !grid.is_loaded() {
+        return;
+    }
+    for object in &stations {
+        grid.set_blocked(object.tile, true);
+    }
+}
+
+fn spawn_energy_bar(mut commands: Commands) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    left: Val::Px(20.0),
+                    bottom: Val::Px(20.0),
+                    width: Val::Px(160.0),
+                    height: Val::Px(14.0),
+                    padding: UiRect::all(Val::Px(2.0)),
+                    ..default()
+                },
+                background_color: Color::BLACK.with_alpha(0.7).into(),
+                ..default()
+            },
+            StateScoped(InNight),
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                NodeBundle {
+                    style: Style {
+                        width: Val::Percent(100.0),
+                        height: Val::Percent(100.0),
+                        ..default()
+                    },
+                    background_color: Color::srgb(0.9, 0.8, 0.2).into(),
+                    ..default()
+                },
+                EnergyBarFill,
+            ));
+        });
+}
+
+fn drain_energy_on_step(mut steps: EventReader<PlayerStepped>, mut energy: ResMut<Energy>) {
+    for _ in steps.read() {
+        energy.spend(STEP_COST);
+    }
+}
+
+fn use_refreshment(
+    mut input: ResMut<GameInput>,
+    player: Res<PosVar>,
+    stations: Query<(&Transform, &Refreshment)>,
+    mut energy: ResMut<Energy>,
+    mut sfx: EventWriter<PlaySfx>,
+) {
+    if !input.just_pressed(GameAction::Interact) {
+        return;
+    }
+
+    for (transform, refreshment) in &stations {
+        let distance = transform.translation.truncate().distance(player.pos_vec.truncate());
+        if distance <= REFRESHMENT_RANGE {
+            input.clear_just_pressed(GameAction::Interact);
+            energy.restore(refreshment.restores);
+            sfx.send(PlaySfx::new(SoundEffect::Drink));
+            info!("Cliff used the {:?}, energy {:.0}", refreshment.kind, energy.current);
+            break;
+        }
+    }
+}
+
+fn warn_low_energy(mut energy: ResMut<Energy>, mut sfx: EventWriter<PlaySfx>) {
+    if energy.is_low() && !energy.low_warned {
+        energy.low_warned = true;
+        sfx.send(PlaySfx::new(SoundEffect::LowHealth));
+    } else if !energy.is_low() && energy.low_warned {
+        energy.low_warned = false;
+    }
+}
+
+fn update_energy_bar(energy: Res<Energy>, mut fill: Query<(&mut Style, &mut BackgroundColor), With<EnergyBarFill>>) {
+    if !energy.is_changed() {
+        return;
+    }
+
+    for (mut style, mut color) in &mut fill {
+        style.width = Val::Percent(energy.fraction() * 100.0);
+        *color = if energy.is_low() {
+            Color::srgb(0.85, 0.2, 0.2).into()
+        } else {
+            Color::srgb(0.9, 0.8, 0.2).into()
+        };
+    }
+}
@@ -0,0 +1,142 @@
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use bevy_tweening::lens::TransformPositionLens;
+use bevy_tweening::{Animator, EaseFunction, Tween};
+use std::time::Duration;
+
+use crate::level::{tile_to_world, LevelGrid, LevelGridBuilt, TILE_SIZE};
+use crate::map_objects::{Guard, MapObject, MapObjectSet, RegisterMapObject};
+use crate::mess::Mess;
+use crate::InNight;
+
+// Furniture placed as "Pushable" objects in the map. Walking into a piece slides it one
+// tile along, as long as the tile behind it is free.
+pub struct FurniturePlugin;
+
+// Just under Cliff, so he is drawn in front of what he pushes.
+const FURNITURE_Z: f32 = 0.95;
+
+#[derive(Component)]
+pub struct Pushable {
+    pub tile: IVec2,
+    // Sprite shown for the piece, relative to the assets folder.
+    pub image: String,
+}
+
+impl Plugin for FurniturePlugin {
+    fn build(&self, app: &mut App) {
+        app.register_map_object("Pushable", |properties| {
+            Ok(Pushable {
+                tile: IVec2::ZERO,
+                image: properties.get_or("image", "move chair.png".to_string())?,
+            })
+        });
+        // After the grid rebuild and the object respawn, so a rebuilt grid is blocked again
+        // the same frame and new pieces are blocked the frame they arrive.
+        app.add_systems(
+            Update,
+            (place_furniture, block_furniture)
+                .chain()
+                .after(MapObjectSet)
+                .run_if(in_state(InNight)),
+        );
+    }
+}
+
+// Snaps new pieces onto the tile they were placed on and gives them their sprite.
+fn place_furniture(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut added: Query<(Entity, &mut Pushable, &MapObject), Added<Pushable>>,
+) {
+    for (entity, mut pushable, object) in &mut added {
+        pushable.tile = object.tile;
+        commands.entity(entity).insert((
+            Sprite {
+                custom_size: Some(Vec2::splat(TILE_SIZE)),
+                ..default()
+            },
+            asset_server.load::<Image>(pushable.image.clone()),
+            Transform::from_translation(tile_to_world(object.tile, FURNITURE_Z)),
+        ));
+    }
+}
+
+// The map knows nothing of the furniture, so block its tiles again whenever the grid
+// is rebuilt or new pieces arrive.
+fn block_furniture(
+    mut built: EventReader<LevelGridBuilt>,
+    mut grid: ResMut<LevelGrid>,
+    pushables: Query<&Pushable>,
+    added: Query<(), Added<Pushable>>,
+) {
+    if built.read().count() == 0 && added.is_empty() {
+        return;
+    }
+    if !grid.is_loaded() {
+        return;
+    }
+    for pushable in &pushables {
+        if grid.is_walkable(pushable.tile) {
+            grid.set_blocked(pushable.tile, true);
+        }
+    }
+}
+
+#[derive(SystemParam)]
+pub struct Furniture<'w, 's> {
+    pushables: Query<'w, 's, (Entity, &'static mut Pushable)>,
+    messes: Query<'w, 's, &'static Mess>,
+    // Furniture keeps its own tile; MapObject only knows where a piece started.
+    objects: Query<'w, 's, (&'static MapObject, Option<&'static Guard>), Without<Pushable>>,
+}
+
+impl Furniture<'_, '_> {
+    // Messes, guards and other map objects sit on walkable tiles the grid does not block.
+    fn occupied(&self, tile: IVec2) -> bool {
+        self.messes.iter().any(|mess| mess.tile == tile)
+            || self
+                .objects
+                .iter()
+                .any(|(object, guard)| guard.map_or(object.tile, |guard| guard.tile(object.tile)) == tile)
+    }
+
+    // Slides the piece on `tile` one tile along `step`, over the same time and with the
+    // same easing as Cliff's own step. False if there is nothing to push or no room.
+    pub fn push(
+        &mut self,
+        commands: &mut Commands,
+        grid: &mut LevelGrid,
+        tile: IVec2,
+        step: IVec2,
+        duration: Duration,
+    ) -> bool {
+        let behind = tile + step;
+        if !grid.is_walkable(behind) || self.occupied(behind) {
+            return false;
+        }
+        let Some((entity, mut pushable)) = self.pushables.iter_mut().find(|(_, pushable)| pushable.tile == tile)
+        else {
+            return false;
+        };
+
+        let tween = Tween::new(
+            EaseFunction::QuadraticInOut,
+            duration,
+            TransformPositionLens {
+                start: tile_to_world(tile, FURNITURE_Z),
+                end: tile_to_world(behind, FURNITURE_Z),
+            },
+        );
+        commands
+            .entity(entity)
+            .remove::<Animator<Transform>>()
+            .insert(Animator::new(tween));
+
+        grid.set_blocked(tile, false);
+        grid.set_blocked(behind, true);
+        pushable.tile = behind;
+        info!("Pushed furniture from ({}, {}) to ({}, {})", tile.x, tile.y, behind.x, behind.y);
+        true
+    }
+}
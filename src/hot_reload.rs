@@ -0,0 +1,84 @@
+use bevy::asset::io::file::FileAssetReader;
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use bevy_ecs_tiled::prelude::{TiledMap, TiledMapHandle};
+use std::fs;
+use std::path::PathBuf;
+use std::time::SystemTime;
+
+// Reloads the running map when its .tmx or any of its .tsx files is saved in Tiled.
+// Tilesets are read by the map loader rather than loaded as assets, so the asset
+// watcher never sees them; polling the files catches both.
+pub struct MapHotReloadPlugin;
+
+const POLL_SECONDS: f32 = 0.5;
+
+#[derive(Resource)]
+struct MapWatch {
+    root: PathBuf,
+    timer: Timer,
+    modified: HashMap<PathBuf, SystemTime>,
+}
+
+impl MapWatch {
+    // True when the file changed since it was last seen; the first sighting only records it.
+    fn changed(&mut self, file: PathBuf) -> bool {
+        let Ok(modified) = fs::metadata(&file).and_then(|metadata| metadata.modified()) else {
+            return false;
+        };
+        match self.modified.insert(file, modified) {
+            Some(previous) => previous != modified,
+            None => false,
+        }
+    }
+}
+
+impl Plugin for MapHotReloadPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(MapWatch {
+            root: FileAssetReader::new("assets").root_path().clone(),
+            timer: Timer::from_seconds(POLL_SECONDS, TimerMode::Repeating),
+            modified: HashMap::default(),
+        });
+        app.add_systems(Update, watch_map_files);
+    }
+}
+
+fn watch_map_files(
+    time: Res<Time<Real>>,
+    mut watch: ResMut<MapWatch>,
+    asset_server: Res<AssetServer>,
+    maps: Res<Assets<TiledMap>>,
+    handles: Query<&TiledMapHandle>,
+) {
+    watch.timer.tick(time.delta());
+    if !watch.timer.just_finished() {
+        return;
+    }
+
+    for handle in &handles {
+        let (Some(path), Some(tiled_map)) = (asset_server.get_path(&handle.0), maps.get(&handle.0)) else {
+            continue;
+        };
+
+        let map_file = watch.root.join(path.path());
+        let mut files = vec![map_file];
+        for tileset in tiled_map.map.tilesets() {
+            let file = if tileset.source.is_absolute() {
+                tileset.source.clone()
+            } else {
+                watch.root.join(&tileset.source)
+            };
+            if !files.contains(&file) {
+                files.push(file);
+            }
+        }
+
+        // Check every file so all of their times stay current.
+        let changed = files.into_iter().filter(|file| watch.changed(file.clone())).count();
+        if changed > 0 {
+            info!("Reloading {} after {} file(s) changed", path, changed);
+            asset_server.reload(path.into_owned());
+        }
+    }
+}
@@ -0,0 +1,292 @@
+use bevy::input::InputSystem;
+use bevy::prelude::*;
+use bevy::ui::UiSystem;
+
+pub struct GameInputPlugin;
+
+// Everything the player can ask for, whichever key or button it came from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GameAction {
+    Up,
+    Down,
+    Left,
+    Right,
+    Interact,
+    Confirm,
+    Back,
+    Flashlight,
+    Mute,
+    VolumeDown,
+    VolumeUp,
+    DebugOverlay,
+    DevTools,
+}
+
+impl GameAction {
+    pub const ALL: [GameAction; 13] = [
+        GameAction::Up,
+        GameAction::Down,
+        GameAction::Left,
+        GameAction::Right,
+        GameAction::Interact,
+        GameAction::Confirm,
+        GameAction::Back,
+        GameAction::Flashlight,
+        GameAction::Mute,
+        GameAction::VolumeDown,
+        GameAction::VolumeUp,
+        GameAction::DebugOverlay,
+        GameAction::DevTools,
+    ];
+
+    fn bit(self) -> u16 {
+        1 << self as u16
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            GameAction::Up => "up",
+            GameAction::Down => "down",
+            GameAction::Left => "left",
+            GameAction::Right => "right",
+            GameAction::Interact => "interact",
+            GameAction::Confirm => "confirm",
+            GameAction::Back => "back",
+            GameAction::Flashlight => "flashlight",
+            GameAction::Mute => "mute",
+            GameAction::VolumeDown => "volume-down",
+            GameAction::VolumeUp => "volume-up",
+            GameAction::DebugOverlay => "debug-overlay",
+            GameAction::DevTools => "dev-tools",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|action| action.name() == name)
+    }
+
+    fn keys(self) -> &'static [KeyCode] {
+        match self {
+            GameAction::Up => &[KeyCode::ArrowUp],
+            GameAction::Down => &[KeyCode::ArrowDown],
+            GameAction::Left => &[KeyCode::ArrowLeft],
+            GameAction::Right => &[KeyCode::ArrowRight],
+            GameAction::Interact => &[KeyCode::Space],
+            GameAction::Confirm => &[KeyCode::Enter],
+            GameAction::Back => &[KeyCode::Escape],
+            GameAction::Flashlight => &[KeyCode::KeyF],
+            GameAction::Mute => &[KeyCode::KeyM],
+            GameAction::VolumeDown => &[KeyCode::Minus],
+            GameAction::VolumeUp => &[KeyCode::Equal],
+            GameAction::DebugOverlay => &[KeyCode::F3],
+            GameAction::DevTools => &[KeyCode::F1],
+        }
+    }
+
+    fn pad_buttons(self) -> &'static [GamepadButtonType] {
+        match self {
+            GameAction::Up => &[GamepadButtonType::DPadUp],
+            GameAction::Down => &[GamepadButtonType::DPadDown],
+            GameAction::Left => &[GamepadButtonType::DPadLeft],
+            GameAction::Right => &[GamepadButtonType::DPadRight],
+            GameAction::Interact => &[GamepadButtonType::West],
+            GameAction::Confirm => &[GamepadButtonType::South],
+            GameAction::Back => &[GamepadButtonType::East],
+            GameAction::Flashlight => &[GamepadButtonType::North],
+            GameAction::Mute
+            | GameAction::VolumeDown
+            | GameAction::VolumeUp
+            | GameAction::DebugOverlay
+            | GameAction::DevTools => &[],
+        }
+    }
+}
+
+// The actions held during one tick, one bit each.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ActionSet(u16);
+
+impl ActionSet {
+    pub fn contains(&self, action: GameAction) -> bool {
+        self.0 & action.bit() != 0
+    }
+
+    pub fn insert(&mut self, action: GameAction) {
+        self.0 |= action.bit();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = GameAction> + '_ {
+        GameAction::ALL.into_iter().filter(|action| self.contains(*action))
+    }
+}
+
+// What the pointer did to a menu button, by the button's place in the menu.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MenuPointer {
+    Hover(usize),
+    Click(usize),
+}
+
+// Everything the player did in one tick, as a recording stores it.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TickInput {
+    pub held: ActionSet,
+    pub menu_pointer: Option<MenuPointer>,
+}
+
+impl TickInput {
+    // Folds in a later frame's input, so nothing done between two ticks is lost.
+    pub fn merge(&mut self, later: TickInput) {
+        self.held.0 |= later.held.0;
+        self.menu_pointer = later_pointer(self.menu_pointer, later.menu_pointer);
+    }
+}
+
+// A click is never undone by the pointer moving on within the same tick.
+fn later_pointer(earlier: Option<MenuPointer>, later: Option<MenuPointer>) -> Option<MenuPointer> {
+    match (earlier, later) {
+        (Some(MenuPointer::Click(index)), Some(MenuPointer::Hover(_))) => Some(MenuPointer::Click(index)),
+        (earlier, None) => earlier,
+        (_, later) => later,
+    }
+}
+
+// Gameplay and menus read actions from here rather than from the devices or the UI,
+// so a recording can stand in for the player.
+#[derive(Resource, Default)]
+pub struct GameInput {
+    held: ActionSet,
+    previous: ActionSet,
+    just_pressed: ActionSet,
+    menu_pointer: Option<MenuPointer>,
+}
+
+impl GameInput {
+    pub fn pressed(&self, action: GameAction) -> bool {
+        self.held.contains(action)
+    }
+
+    pub fn just_pressed(&self, action: GameAction) -> bool {
+        self.just_pressed.contains(action)
+    }
+
+    // Takes the press for one system so nothing after it sees it this tick.
+    pub fn clear_just_pressed(&mut self, action: GameAction) -> bool {
+        let pressed = self.just_pressed(action);
+        self.just_pressed.0 &= !action.bit();
+        pressed
+    }
+
+    // For on-screen buttons that stand in for a key, such as the dialogue panel.
+    pub fn press(&mut self, action: GameAction) {
+        self.held.insert(action);
+        if !self.previous.contains(action) {
+            self.just_pressed.insert(action);
+        }
+    }
+
+    pub fn menu_pointer(&self) -> Option<MenuPointer> {
+        self.menu_pointer
+    }
+
+    pub fn point_at_menu(&mut self, pointer: MenuPointer) {
+        self.menu_pointer = later_pointer(self.menu_pointer, Some(pointer));
+    }
+
+    pub fn snapshot(&self) -> TickInput {
+        TickInput {
+            held: self.held,
+            menu_pointer: self.menu_pointer,
+        }
+    }
+
+    // Swaps this tick's input for a recorded one.
+    pub fn replace(&mut self, input: TickInput) {
+        self.held = input.held;
+        self.just_pressed = ActionSet(input.held.0 & !self.previous.0);
+        self.menu_pointer = input.menu_pointer;
+    }
+
+    fn update(&mut self, held: ActionSet) {
+        self.previous = self.held;
+        self.replace(TickInput {
+            held,
+            menu_pointer: None,
+        });
+    }
+}
+
+// Runs after bevy's input and before anything that reads GameInput.
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct GameInputSet;
+
+// UI systems that turn clicks on their buttons into GameInput, after the devices are read.
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PointerInputSet;
+
+// Everything Interact can do in the world, in the order each gets first pick. The
+// first one with something in reach takes the press, so one tap does one thing.
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub enum InteractSet {
+    Disturbance,
+    Switch,
+    Door,
+    Mess,
+    Refreshment,
+}
+
+impl Plugin for GameInputPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GameInput>();
+        app.configure_sets(PreUpdate, GameInputSet.after(InputSystem));
+        app.configure_sets(
+            PreUpdate,
+            PointerInputSet
+                .in_set(GameInputSet)
+                .after(read_game_input)
+                .after(UiSystem::Focus),
+        );
+        app.configure_sets(
+            Update,
+            (
+                InteractSet::Disturbance,
+                InteractSet::Switch,
+                InteractSet::Door,
+                InteractSet::Mess,
+                InteractSet::Refreshment,
+            )
+                .chain(),
+        );
+        app.add_systems(PreUpdate, read_game_input.in_set(GameInputSet));
+    }
+}
+
+pub fn read_game_input(
+    keys: Res<ButtonInput<KeyCode>>,
+    gamepads: Res<Gamepads>,
+    gamepad_buttons: Res<ButtonInput<GamepadButton>>,
+    mut input: ResMut<GameInput>,
+) {
+    let mut held = ActionSet::default();
+    for action in GameAction::ALL {
+        // A tap that starts and ends within one frame still counts for that tick.
+        let key_held = action
+            .keys()
+            .iter()
+            .any(|key| keys.pressed(*key) || keys.just_pressed(*key));
+        let pad_held = action.pad_buttons().iter().any(|button_type| {
+            gamepads.iter().any(|gamepad| {
+                let button = GamepadButton::new(gamepad, *button_type);
+                gamepad_buttons.pressed(button) || gamepad_buttons.just_pressed(button)
+            })
+        });
+        if key_held || pad_held {
+            held.insert(action);
+        }
+    }
+    input.update(held);
+}
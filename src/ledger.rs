@@ -0,0 +1,22 @@
+use bevy::prelude::*;
+
+// Money Cliff has made or cost the company over the night.
+#[derive(Resource, Default)]
+pub struct Ledger {
+    pub earnings: u32,
+    pub damages: u32,
+}
+
+impl Ledger {
+    pub fn earn(&mut self, amount: u32) {
+        self.earnings += amount;
+    }
+
+    pub fn charge(&mut self, amount: u32) {
+        self.damages += amount;
+    }
+
+    pub fn balance(&self) -> i64 {
+        self.earnings as i64 - self.damages as i64
+    }
+}
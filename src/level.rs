@@ -0,0 +1,341 @@
+use bevy::prelude::*;
+use bevy::utils::HashSet;
+use bevy_ecs_tiled::prelude::{TiledMap, TiledMapHandle};
+use bevy_tweening::Animator;
+use std::collections::VecDeque;
+use tiled::{LayerType, ObjectShape, PropertyValue};
+
+use crate::map_objects::RegisterMapObject;
+use crate::{InNight, PosVar};
+
+pub struct LevelPlugin;
+
+pub const TILE_SIZE: f32 = 24.0;
+
+// Local id of the dark tile painted around the outside of the office.
+const OUTSIDE_TILE_ID: u32 = 68;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum RoomKind {
+    Office,
+    Lobby,
+    BreakRoom,
+    Conference,
+    Restroom,
+    Hallway,
+}
+
+impl RoomKind {
+    fn from_property(value: &str) -> Option<Self> {
+        match value {
+            "office" => Some(RoomKind::Office),
+            "lobby" => Some(RoomKind::Lobby),
+            "break_room" => Some(RoomKind::BreakRoom),
+            "conference" => Some(RoomKind::Conference),
+            "restroom" => Some(RoomKind::Restroom),
+            "hallway" => Some(RoomKind::Hallway),
+            _ => None,
+        }
+    }
+}
+
+// Sent after a saved map has been reloaded and the grid rebuilt from it, so anything
+// spawned from the old map can be rebuilt too.
+#[derive(Event)]
+pub struct MapReloaded;
+
+// Sent whenever the grid is built from a map, so things that add their own blocked
+// tiles know to add them again.
+#[derive(Event)]
+pub struct LevelGridBuilt;
+
+// Rebuilding the grid from the current map. Anything that blocks tiles of its own runs
+// after this, so the tiles it blocks are not lost to a rebuild.
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct LevelGridSet;
+
+pub struct Room {
+    pub kind: RoomKind,
+    pub bounds: Rect,
+}
+
+#[derive(Resource, Default)]
+pub struct LevelGrid {
+    pub width: i32,
+    pub height: i32,
+    blocked: Vec<bool>,
+    pub obstacles: Vec<Rect>,
+    pub rooms: Vec<Room>,
+}
+
+impl LevelGrid {
+    pub fn from_map(map: &tiled::Map) -> Self {
+        let width = map.width as i32;
+        let height = map.height as i32;
+        let map_height_px = (map.height * map.tile_height) as f32;
+
+        let mut grid = LevelGrid {
+            width,
+            height,
+            blocked: vec![false; (width * height) as usize],
+            ..default()
+        };
+
+        for layer in map.layers() {
+            match layer.layer_type() {
+                LayerType::Tiles(tiles) if layer.name == "Floor" => {
+                    for y in 0..height {
+                        for x in 0..width {
+                            let outside = match tiles.get_tile(x, y) {
+                                Some(tile) => tile.id() == OUTSIDE_TILE_ID,
+                                None => true,
+                            };
+                            if outside {
+                                // Tiled rows run top-down, ours run bottom-up.
+                                grid.set_blocked(IVec2::new(x, height - 1 - y), true);
+                            }
+                        }
+                    }
+                }
+                LayerType::Objects(objects) if layer.name == "Obstacle" => {
+                    for object in objects.objects() {
+                        if let ObjectShape::Rect { width, height } = object.shape {
+                            grid.obstacles
+                                .push(tiled_rect(map_height_px, object.x, object.y, width, height));
+                        }
+                    }
+                }
+                LayerType::Objects(objects) if layer.name == "Rooms" => {
+                    for object in objects.objects() {
+                        let ObjectShape::Rect { width, height } = object.shape else {
+                            continue;
+                        };
+                        let kind = match object.properties.get("kind") {
+                            Some(PropertyValue::StringValue(kind)) => RoomKind::from_property(kind),
+                            _ => None,
+                        };
+                        let Some(kind) = kind else {
+                            warn!("Room '{}' has no valid kind property", object.name);
+                            continue;
+                        };
+                        grid.rooms.push(Room {
+                            kind,
+                            bounds: tiled_rect(map_height_px, object.x, object.y, width, height),
+                        });
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        for y in 0..height {
+            for x in 0..width {
+                let tile = IVec2::new(x, y);
+                let center = tile_to_world(tile, 0.0).truncate();
+                if grid.obstacles.iter().any(|rect| rect.contains(center)) {
+                    grid.set_blocked(tile, true);
+                }
+            }
+        }
+
+        grid
+    }
+
+    pub fn is_loaded(&self) -> bool {
+        self.width > 0 && self.height > 0
+    }
+
+    pub fn in_bounds(&self, tile: IVec2) -> bool {
+        tile.x >= 0 && tile.y >= 0 && tile.x < self.width && tile.y < self.height
+    }
+
+    pub fn is_walkable(&self, tile: IVec2) -> bool {
+        self.in_bounds(tile) && !self.blocked[self.index(tile)]
+    }
+
+    pub fn set_blocked(&mut self, tile: IVec2, blocked: bool) {
+        if self.in_bounds(tile) {
+            let index = self.index(tile);
+            self.blocked[index] = blocked;
+        }
+    }
+
+    pub fn room_at(&self, tile: IVec2) -> Option<&Room> {
+        let center = tile_to_world(tile, 0.0).truncate();
+        self.rooms.iter().find(|room| room.bounds.contains(center))
+    }
+
+    pub fn room_kind_at(&self, tile: IVec2) -> RoomKind {
+        self.room_at(tile).map(|room| room.kind).unwrap_or(RoomKind::Hallway)
+    }
+
+    pub fn walkable_neighbours(&self, tile: IVec2) -> impl Iterator<Item = IVec2> + '_ {
+        [IVec2::X, IVec2::NEG_X, IVec2::Y, IVec2::NEG_Y]
+            .into_iter()
+            .map(move |offset| tile + offset)
+            .filter(|neighbour| self.is_walkable(*neighbour))
+    }
+
+    pub fn walkable_tiles_in(&self, room: &Room) -> Vec<IVec2> {
+        let min = world_to_tile(room.bounds.min.extend(0.0));
+        let max = world_to_tile(room.bounds.max.extend(0.0));
+        let mut tiles = Vec::new();
+        for y in min.y..=max.y {
+            for x in min.x..=max.x {
+                let tile = IVec2::new(x, y);
+                if self.is_walkable(tile) && room.bounds.contains(tile_to_world(tile, 0.0).truncate()) {
+                    tiles.push(tile);
+                }
+            }
+        }
+        tiles
+    }
+
+    // Closest walkable tile by steps through the map, walls or not.
+    pub fn nearest_walkable(&self, tile: IVec2) -> Option<IVec2> {
+        let start = tile.clamp(IVec2::ZERO, IVec2::new(self.width - 1, self.height - 1));
+        let mut seen = HashSet::from([start]);
+        let mut queue = VecDeque::from([start]);
+        while let Some(current) = queue.pop_front() {
+            if self.is_walkable(current) {
+                return Some(current);
+            }
+            for offset in [IVec2::X, IVec2::NEG_X, IVec2::Y, IVec2::NEG_Y] {
+                let next = current + offset;
+                if self.in_bounds(next) && seen.insert(next) {
+                    queue.push_back(next);
+                }
+            }
+        }
+        None
+    }
+
+    fn index(&self, tile: IVec2) -> usize {
+        (tile.y * self.width + tile.x) as usize
+    }
+}
+
+// Tile (0, 0) is centered on the world origin.
+pub fn world_to_tile(position: Vec3) -> IVec2 {
+    IVec2::new(
+        (position.x / TILE_SIZE).round() as i32,
+        (position.y / TILE_SIZE).round() as i32,
+    )
+}
+
+pub fn tile_to_world(tile: IVec2, z: f32) -> Vec3 {
+    Vec3::new(tile.x as f32 * TILE_SIZE, tile.y as f32 * TILE_SIZE, z)
+}
+
+// Converts a point in Tiled pixel space (origin top-left, y down) to world space.
+pub fn tiled_to_world(map_height_px: f32, x: f32, y: f32) -> Vec2 {
+    Vec2::new(x - TILE_SIZE / 2.0, map_height_px - y - TILE_SIZE / 2.0)
+}
+
+fn tiled_rect(map_height_px: f32, x: f32, y: f32, width: f32, height: f32) -> Rect {
+    Rect::from_corners(
+        tiled_to_world(map_height_px, x, y),
+        tiled_to_world(map_height_px, x + width, y + height),
+    )
+}
+
+impl Plugin for LevelPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<LevelGrid>();
+        app.add_event::<MapReloaded>();
+        app.add_event::<LevelGridBuilt>();
+        // Rooms are part of the grid, see LevelGrid::from_map.
+        app.ignore_map_object("Room");
+        app.add_systems(OnEnter(InNight), reset_level_grid);
+        app.add_systems(
+            Update,
+            (rebuild_level_grid, build_level_grid, keep_player_on_map)
+                .chain()
+                .in_set(LevelGridSet),
+        );
+    }
+}
+
+// The map loads asynchronously, so build the grid the first frame it is available.
+// The next night's map rebuilds the grid once it has loaded.
+fn reset_level_grid(mut grid: ResMut<LevelGrid>) {
+    *grid = LevelGrid::default();
+}
+
+// A modified map arrives as a new version of the same asset, so drop the old grid
+// and let build_level_grid pick up the new one.
+fn rebuild_level_grid(
+    mut events: EventReader<AssetEvent<TiledMap>>,
+    mut grid: ResMut<LevelGrid>,
+    mut reloaded: EventWriter<MapReloaded>,
+    handles: Query<&TiledMapHandle>,
+) {
+    let modified = events.read().any(|event| match event {
+        AssetEvent::Modified { id } => handles.iter().any(|handle| handle.0.id() == *id),
+        _ => false,
+    });
+    if modified && grid.is_loaded() {
+        info!("Map reloaded, rebuilding the level");
+        *grid = LevelGrid::default();
+        reloaded.send(MapReloaded);
+    }
+}
+
+fn build_level_grid(
+    mut grid: ResMut<LevelGrid>,
+    mut built: EventWriter<LevelGridBuilt>,
+    maps: Res<Assets<TiledMap>>,
+    handles: Query<&TiledMapHandle>,
+) {
+    if grid.is_loaded() {
+        return;
+    }
+
+    for handle in &handles {
+        if let Some(tiled_map) = maps.get(&handle.0) {
+            *grid = LevelGrid::from_map(&tiled_map.map);
+            info!(
+                "Level grid built: {}x{} tiles, {} obstacles, {} rooms",
+                grid.width,
+                grid.height,
+                grid.obstacles.len(),
+                grid.rooms.len()
+            );
+            built.send(LevelGridBuilt);
+        }
+    }
+}
+
+// Cliff keeps the same tile through a reload unless the new map put something there.
+fn keep_player_on_map(
+    mut commands: Commands,
+    mut reloaded: EventReader<MapReloaded>,
+    grid: Res<LevelGrid>,
+    player: Option<ResMut<PosVar>>,
+    mut transforms: Query<&mut Transform>,
+) {
+    if reloaded.read().count() == 0 || !grid.is_loaded() {
+        return;
+    }
+    let Some(mut player) = player else {
+        return;
+    };
+
+    let tile = world_to_tile(player.pos_vec);
+    if grid.is_walkable(tile) {
+        return;
+    }
+    let Some(target) = grid.nearest_walkable(tile) else {
+        warn!("Reloaded map has no walkable tiles");
+        return;
+    };
+
+    info!("Tile ({}, {}) is blocked now, moving the player to ({}, {})", tile.x, tile.y, target.x, target.y);
+    let position = tile_to_world(target, player.pos_vec.z);
+    player.pos_vec = position;
+    player.in_anim = false;
+    commands.entity(player.id).remove::<Animator<Transform>>();
+    if let Ok(mut transform) = transforms.get_mut(player.id) {
+        transform.translation = position;
+    }
+}
@@ -0,0 +1,322 @@
+use bevy::prelude::*;
+use bevy::render::render_asset::RenderAssetUsages;
+use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat};
+use bevy::render::texture::ImageSampler;
+
+use crate::audio::{PlaySfx, SoundEffect};
+use crate::input::{GameAction, GameInput, InteractSet};
+use crate::level::{world_to_tile, LevelGrid, MapReloaded, TILE_SIZE};
+use crate::map_objects::{ObjectProperties, RegisterMapObject};
+use crate::text::{no_modal_open, GameTime};
+use crate::{GameState, InNight, PosVar};
+
+pub struct LightingPlugin;
+
+// Lightmap texels per map tile; 4 keeps the falloff smooth without much CPU work.
+const TEXELS_PER_TILE: u32 = 4;
+const LIGHTMAP_Z: f32 = 5.0;
+
+// Ambient darkness at the start of the shift and once night has fully fallen.
+const DUSK_DARKNESS: f32 = 0.15;
+const NIGHT_DARKNESS: f32 = 0.88;
+const NIGHTFALL_MINUTES: f32 = 240.0;
+
+const FLASHLIGHT_RANGE: f32 = 110.0;
+const FLASHLIGHT_HALF_ANGLE: f32 = 0.45;
+const SWITCH_RANGE: i32 = 1;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LightKind {
+    Lamp,
+    Monitor,
+    ExitSign,
+}
+
+impl LightKind {
+    fn intensity(&self) -> f32 {
+        match self {
+            LightKind::Lamp => 1.0,
+            LightKind::Monitor => 0.5,
+            LightKind::ExitSign => 0.35,
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct LightSource {
+    pub kind: LightKind,
+    pub radius: f32,
+    pub room: Option<String>,
+    pub on: bool,
+}
+
+#[derive(Component)]
+pub struct LightSwitch {
+    pub room: String,
+}
+
+#[derive(Resource)]
+pub struct Flashlight {
+    pub on: bool,
+}
+
+#[derive(Component)]
+struct LightmapSprite;
+
+#[derive(Resource)]
+struct Lightmap {
+    image: Handle<Image>,
+    width: u32,
+    height: u32,
+    // World position of the top-left corner of the lightmap.
+    origin: Vec2,
+}
+
+impl Plugin for LightingPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Flashlight { on: true });
+        app.register_map_object("Light", |properties| {
+            let kind = match properties.get_or("kind", "lamp".to_string())?.as_str() {
+                "lamp" => LightKind::Lamp,
+                "monitor" => LightKind::Monitor,
+                "exit_sign" => LightKind::ExitSign,
+                kind => return Err(format!("unknown light kind '{}'", kind)),
+            };
+            light_source(kind, properties)
+        });
+        for (class, kind) in [
+            ("Lamp", LightKind::Lamp),
+            ("Monitor", LightKind::Monitor),
+            ("ExitSign", LightKind::ExitSign),
+        ] {
+            app.register_map_object(class, move |properties| light_source(kind, properties));
+        }
+        app.register_map_object("LightSwitch", |properties| {
+            Ok(LightSwitch {
+                room: properties.require("room")?,
+            })
+        });
+        app.add_systems(OnEnter(InNight), reset_lighting);
+        // Also while paused, so a reload is never missed.
+        app.add_systems(Update, clear_lightmap.before(spawn_lightmap));
+        app.add_systems(
+            Update,
+            (
+                spawn_lightmap,
+                toggle_flashlight.run_if(no_modal_open),
+                use_light_switch.run_if(no_modal_open).in_set(InteractSet::Switch),
+                update_lightmap,
+            )
+                .chain()
+                .run_if(in_state(GameState::Playing)),
+        );
+    }
+}
+
+fn reset_lighting(mut commands: Commands) {
+    commands.insert_resource(Flashlight { on: true });
+    commands.remove_resource::<Lightmap>();
+}
+
+fn light_source(kind: LightKind, properties: &ObjectProperties) -> Result<LightSource, String> {
+    Ok(LightSource {
+        kind,
+        radius: properties.get_or("radius", TILE_SIZE * 3.0)?,
+        room: properties.get("room")?,
+        on: properties.get_or("on", true)?,
+    })
+}
+
+// The map may have changed size, so a reload starts the lightmap over. The lights
+// themselves are map objects and get respawned with the rest.
+fn clear_lightmap(
+    mut commands: Commands,
+    mut reloaded: EventReader<MapReloaded>,
+    spawned: Query<Entity, With<LightmapSprite>>,
+) {
+    if reloaded.read().count() == 0 {
+        return;
+    }
+    for entity in &spawned {
+        commands.entity(entity).despawn_recursive();
+    }
+    commands.remove_resource::<Lightmap>();
+}
+
+fn spawn_lightmap(
+    mut commands: Commands,
+    grid: Res<LevelGrid>,
+    lightmap: Option<Res<Lightmap>>,
+    mut images: ResMut<Assets<Image>>,
+) {
+    if lightmap.is_some() || !grid.is_loaded() {
+        return;
+    }
+
+    let width = grid.width as u32 * TEXELS_PER_TILE;
+    let height = grid.height as u32 * TEXELS_PER_TILE;
+    let mut image = Image::new_fill(
+        Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        &[0, 0, 0, 0],
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::default(),
+    );
+    // The rest of the game uses nearest filtering; light should blend between texels.
+    image.sampler = ImageSampler::linear();
+    let handle = images.add(image);
+
+    let map_size = Vec2::new(grid.width as f32, grid.height as f32) * TILE_SIZE;
+    let bottom_left = Vec2::splat(-TILE_SIZE / 2.0);
+
+    commands.spawn((
+        SpriteBundle {
+            sprite: Sprite {
+                custom_size: Some(map_size),
+                ..default()
+            },
+            texture: handle.clone(),
+            transform: Transform::from_translation((bottom_left + map_size / 2.0).extend(LIGHTMAP_Z)),
+            ..default()
+        },
+        LightmapSprite,
+        StateScoped(InNight),
+    ));
+    commands.insert_resource(Lightmap {
+        image: handle,
+        width,
+        height,
+        origin: Vec2::new(bottom_left.x, bottom_left.y + map_size.y),
+    });
+}
+
+fn toggle_flashlight(input: Res<GameInput>, mut flashlight: ResMut<Flashlight>) {
+    if input.just_pressed(GameAction::Flashlight) {
+        flashlight.on = !flashlight.on;
+    }
+}
+
+fn use_light_switch(
+    mut input: ResMut<GameInput>,
+    player: Res<PosVar>,
+    switches: Query<(&Transform, &LightSwitch)>,
+    mut lights: Query<&mut LightSource>,
+    mut sfx: EventWriter<PlaySfx>,
+) {
+    if !input.just_pressed(GameAction::Interact) {
+        return;
+    }
+
+    let player_tile = world_to_tile(player.pos_vec);
+    let Some((_, switch)) = switches.iter().find(|(transform, _)| {
+        (world_to_tile(transform.translation) - player_tile).abs().element_sum() <= SWITCH_RANGE
+    }) else {
+        return;
+    };
+    input.clear_just_pressed(GameAction::Interact);
+
+    // Exit signs stay lit whatever the switch says.
+    let mut room_lights: Vec<_> = lights
+        .iter_mut()
+        .filter(|light| light.kind != LightKind::ExitSign)
+        .filter(|light| light.room.as_deref() == Some(switch.room.as_str()))
+        .collect();
+    let turn_on = !room_lights.iter().any(|light| light.on);
+    for light in &mut room_lights {
+        light.on = turn_on;
+    }
+
+    sfx.send(PlaySfx::new(SoundEffect::Click));
+    info!("Lights in {} turned {}", switch.room, if turn_on { "on" } else { "off" });
+}
+
+// Where Cliff stood and faced when the lightmap was last drawn.
+type LitFrom = (Vec3, Option<Vec3>);
+
+#[allow(clippy::too_many_arguments)]
+fn update_lightmap(
+    lightmap: Option<Res<Lightmap>>,
+    mut images: ResMut<Assets<Image>>,
+    game_time: Res<GameTime>,
+    player: Res<PosVar>,
+    flashlight: Res<Flashlight>,
+    transforms: Query<&Transform>,
+    lights: Query<(&Transform, &LightSource)>,
+    changed_lights: Query<(), Changed<LightSource>>,
+    mut lit_from: Local<Option<LitFrom>>,
+) {
+    let Some(lightmap) = lightmap else {
+        return;
+    };
+
+    // Light Cliff where he is drawn; PosVar already holds the end of the step he is on.
+    let Ok(player_transform) = transforms.get(player.id) else {
+        return;
+    };
+    let player_view = (player_transform.translation, player.last_direction);
+    let unchanged = !lightmap.is_added()
+        && !game_time.is_changed()
+        && !flashlight.is_changed()
+        && changed_lights.is_empty()
+        && *lit_from == Some(player_view);
+    if unchanged {
+        return;
+    }
+    // Borrowing the image mutably is what re-uploads it, so only do that when redrawing.
+    let Some(image) = images.get_mut(&lightmap.image) else {
+        return;
+    };
+    *lit_from = Some(player_view);
+
+    let night = (game_time.elapsed_minutes() as f32 / NIGHTFALL_MINUTES).min(1.0);
+    let ambient = DUSK_DARKNESS + (NIGHT_DARKNESS - DUSK_DARKNESS) * night;
+
+    let lit: Vec<(Vec2, &LightSource)> = lights
+        .iter()
+        .filter(|(_, light)| light.on)
+        .map(|(transform, light)| (transform.translation.truncate(), light))
+        .collect();
+
+    let facing = player
+        .last_direction
+        .map(|direction| direction.truncate().normalize_or_zero())
+        .unwrap_or(Vec2::NEG_Y);
+    let player_position = player_transform.translation.truncate();
+    let texel_size = TILE_SIZE / TEXELS_PER_TILE as f32;
+
+    for y in 0..lightmap.height {
+        for x in 0..lightmap.width {
+            let point = lightmap.origin
+                + Vec2::new((x as f32 + 0.5) * texel_size, -(y as f32 + 0.5) * texel_size);
+
+            let mut light = 0.0;
+            for (position, source) in &lit {
+                let distance = point.distance(*position);
+                if distance < source.radius {
+                    let falloff = 1.0 - distance / source.radius;
+                    light += source.kind.intensity() * falloff * falloff;
+                }
+            }
+
+            if flashlight.on {
+                let offset = point - player_position;
+                let distance = offset.length();
+                if distance < FLASHLIGHT_RANGE && distance > 0.0 {
+                    let angle = facing.angle_between(offset / distance).abs();
+                    if angle < FLASHLIGHT_HALF_ANGLE {
+                        let edge = 1.0 - angle / FLASHLIGHT_HALF_ANGLE;
+                        light += (1.0 - distance / FLASHLIGHT_RANGE) * edge.sqrt();
+                    }
+                }
+            }
+
+            let alpha = ambient * (1.0 - light.min(1.0));
+            let index = ((y * lightmap.width + x) * 4 + 3) as usize;
+            image.data[index] = (alpha * 255.0) as u8;
+        }
+    }
+}
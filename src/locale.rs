@@ -0,0 +1,203 @@
+use bevy::prelude::*;
+use std::collections::HashMap;
+use std::fmt::Display;
+
+use crate::settings::UserSettings;
+use crate::text::GameTime;
+
+pub struct LocalePlugin;
+
+// Shifts start at 5:00 p.m.
+const SHIFT_START_MINUTE: u32 = 17 * 60;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Language {
+    English,
+    German,
+}
+
+impl Language {
+    const ALL: [Language; 2] = [Language::English, Language::German];
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            Language::English => "en-US",
+            Language::German => "de-DE",
+        }
+    }
+
+    pub fn from_code(code: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|language| language.code() == code)
+    }
+
+    fn next(&self) -> Self {
+        let index = Self::ALL.iter().position(|language| language == self).unwrap_or(0);
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+
+    // Translations are small, so they are built into the binary.
+    fn source(&self) -> &'static str {
+        match self {
+            Language::English => include_str!("../locales/en-US.ftl"),
+            Language::German => include_str!("../locales/de-DE.ftl"),
+        }
+    }
+
+    fn uses_24_hour_clock(&self) -> bool {
+        match self {
+            Language::English => false,
+            Language::German => true,
+        }
+    }
+}
+
+// Switches to the next language in the list.
+#[derive(Event, Clone)]
+pub struct CycleLanguage;
+
+#[derive(Resource)]
+pub struct Locale {
+    pub language: Language,
+    messages: HashMap<String, String>,
+    // English, for keys a translation has not caught up with yet.
+    fallback: HashMap<String, String>,
+}
+
+impl FromWorld for Locale {
+    fn from_world(world: &mut World) -> Self {
+        let code = world
+            .get_resource::<UserSettings>()
+            .map(|settings| settings.language.clone())
+            .unwrap_or_default();
+        let language = Language::from_code(&code).unwrap_or_else(|| {
+            warn!("Unknown language '{}', using English", code);
+            Language::English
+        });
+        Locale::new(language)
+    }
+}
+
+impl Locale {
+    pub fn new(language: Language) -> Self {
+        Self {
+            language,
+            messages: parse_messages(language.source()),
+            fallback: parse_messages(Language::English.source()),
+        }
+    }
+
+    pub fn get(&self, key: &str) -> String {
+        self.format(key, &[])
+    }
+
+    pub fn contains(&self, key: &str) -> bool {
+        self.messages.contains_key(key) || self.fallback.contains_key(key)
+    }
+
+    // Fills `{ $name }` placeholders from `args` and `{ other-key }` from other messages.
+    pub fn format(&self, key: &str, args: &[(&str, &dyn Display)]) -> String {
+        self.format_nested(key, args, &mut Vec::new())
+    }
+
+    // `expanding` holds the messages already being filled in further up, so a message
+    // that refers back to one of them is left as written instead of recursing forever.
+    fn format_nested<'a>(&'a self, key: &'a str, args: &[(&str, &dyn Display)], expanding: &mut Vec<&'a str>) -> String {
+        let Some(pattern) = self.messages.get(key).or_else(|| self.fallback.get(key)) else {
+            warn!("Missing translation for '{}'", key);
+            return key.to_string();
+        };
+        expanding.push(key);
+
+        let mut text = String::new();
+        let mut rest = pattern.as_str();
+        while let Some(open) = rest.find('{') {
+            text.push_str(&rest[..open]);
+            let Some(close) = rest[open..].find('}') else {
+                break;
+            };
+            let placeable = rest[open + 1..open + close].trim();
+            if let Some(name) = placeable.strip_prefix('$') {
+                match args.iter().find(|(arg, _)| *arg == name) {
+                    Some((_, value)) => text.push_str(&value.to_string()),
+                    None => warn!("Missing argument '{}' for '{}'", name, key),
+                }
+            } else if expanding.contains(&placeable) {
+                warn!("'{}' refers back to '{}'", key, placeable);
+                text.push_str(&rest[open..open + close + 1]);
+            } else {
+                text.push_str(&self.format_nested(placeable, args, expanding));
+            }
+            rest = &rest[open + close + 1..];
+        }
+        text.push_str(rest);
+        expanding.pop();
+        text
+    }
+
+    // The wall clock for the current point in the shift, in the locale's convention.
+    pub fn clock(&self, game_time: &GameTime) -> String {
+        let minute_of_day = (SHIFT_START_MINUTE + game_time.elapsed_minutes()) % (24 * 60);
+        let (hours, minutes) = (minute_of_day / 60, minute_of_day % 60);
+        let minutes = format!("{:02}", minutes);
+
+        if self.language.uses_24_hour_clock() {
+            return self.format("clock-24h", &[("hours", &format!("{:02}", hours)), ("minutes", &minutes)]);
+        }
+        let key = if hours < 12 { "clock-am" } else { "clock-pm" };
+        let hours = match hours % 12 {
+            0 => 12,
+            hours => hours,
+        };
+        self.format(key, &[("hours", &format!("{:02}", hours)), ("minutes", &minutes)])
+    }
+}
+
+// A small subset of Fluent: `key = value` messages, indented continuation lines and
+// `#` comments. Attributes, selectors and functions are not supported.
+fn parse_messages(source: &str) -> HashMap<String, String> {
+    let mut messages: HashMap<String, String> = HashMap::new();
+    let mut current: Option<String> = None;
+
+    for line in source.lines() {
+        if line.trim_start().starts_with('#') || line.trim().is_empty() {
+            current = None;
+            continue;
+        }
+        if line.starts_with(char::is_whitespace) {
+            if let Some(value) = current.as_ref().and_then(|key| messages.get_mut(key)) {
+                value.push(' ');
+                value.push_str(line.trim());
+            }
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=') else {
+            warn!("Skipping malformed translation line '{}'", line);
+            continue;
+        };
+        let key = key.trim().to_string();
+        messages.insert(key.clone(), value.trim().to_string());
+        current = Some(key);
+    }
+    messages
+}
+
+impl Plugin for LocalePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Locale>();
+        app.add_event::<CycleLanguage>();
+        app.add_systems(Update, cycle_language);
+    }
+}
+
+fn cycle_language(
+    mut requests: EventReader<CycleLanguage>,
+    mut locale: ResMut<Locale>,
+    mut settings: ResMut<UserSettings>,
+) {
+    for _ in requests.read() {
+        let language = locale.language.next();
+        info!("Language: {}", language.code());
+        *locale = Locale::new(language);
+        settings.language = language.code().to_string();
+    }
+}
@@ -1,4 +1,4 @@
-use bevy::{prelude::*, render::camera::ScalingMode, transform::commands, window::PrimaryWindow};
+use bevy::{prelude::*, render::camera::ScalingMode, window::PrimaryWindow};
 use bevy_ecs_tiled::{TiledMapHandle, TiledMapPlugin};
 use bevy_ecs_tilemap::prelude::*;
 use bevy_tweening::Tween;
@@ -16,19 +16,63 @@ use crate::mainmenu::MenuPlugin;
 
 
 use bevy_spritesheet_animation::prelude::*;
-use bevy_egui::{egui, EguiContexts, EguiPlugin};
 use bevy::prelude::Window;
 use bevy_text_popup::TextPopupPlugin;
 use text::handle_next_popup;
 use text::welcome_setup;
 use text::game_ui;
 use text::update_time;
+use text::update_tasks;
+use text::PopupState;
 
 mod audio;
-use audio::GameAudioPlugin;
+mod debug;
+#[cfg(feature = "dev")]
+mod dev;
+mod dialogue;
+mod energy;
+mod furniture;
+mod hot_reload;
+mod input;
+mod ledger;
+mod level;
+mod lighting;
+mod locale;
+mod map_objects;
+mod mess;
+mod music;
+mod night;
+mod night_events;
+mod replay;
+mod rng;
+mod settings;
+mod time_scale;
+mod transition;
+
+#[cfg(test)]
+mod tests;
+use audio::{GameAudioPlugin, PlaySfx, SoundEffect};
+use debug::DebugOverlayPlugin;
+use hot_reload::MapHotReloadPlugin;
+use dialogue::DialoguePlugin;
+use energy::{Energy, EnergyPlugin, RESTED_STEP};
+use furniture::{Furniture, FurniturePlugin};
+use input::{GameAction, GameInput, GameInputPlugin};
+use ledger::Ledger;
+use level::{world_to_tile, LevelGrid, LevelPlugin, TILE_SIZE};
+use lighting::LightingPlugin;
+use locale::LocalePlugin;
+use map_objects::MapObjectsPlugin;
+use mess::MessPlugin;
+use music::MusicPlugin;
+use night::{CurrentLevel, NightPlugin};
+use night_events::NightEventsPlugin;
+use replay::ReplayPlugin;
+use rng::GameRng;
+use settings::SettingsPlugin;
+use time_scale::TimeScalePlugin;
+use transition::TransitionPlugin;
 use bevy_kira_audio::AudioPlugin;
-use audio::play_button_press;
-use audio::ButtonPressTriggered;
 
 
 
@@ -40,11 +84,28 @@ pub enum GameState {
     Settings,
     Playing,
     Paused,
+    // Passed through on the way to a fresh night, so restarting tears the old one down.
+    Loading,
+    // The end-of-night report. The night itself is already torn down.
+    Results,
     Exit,
 }
 
-#[derive(Component)]
-struct Collider;
+// A night is underway, whether or not it is paused. Everything spawned for it is
+// scoped to this state and goes away when the player leaves for the title.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct InNight;
+
+impl ComputedStates for InNight {
+    type SourceStates = GameState;
+
+    fn compute(sources: GameState) -> Option<Self> {
+        match sources {
+            GameState::Playing | GameState::Paused => Some(InNight),
+            _ => None,
+        }
+    }
+}
 
 #[derive(Resource)]
 struct RootEntity(Entity);
@@ -53,12 +114,13 @@ fn despawn_state(mut commands: Commands, root: Res<RootEntity>) {
     commands.entity(root.0).despawn_recursive();
 }
 
-fn quit_game(mut exit: EventWriter<AppExit>) {
-    exit.send(AppExit::Success);
+// The old night has been torn down by now; start the new one.
+fn start_next_night(mut game_state: ResMut<NextState<GameState>>) {
+    game_state.set(GameState::Playing);
 }
 
-fn create_camera(mut commands: Commands) {
-    commands.spawn(Camera2dBundle::default());
+fn quit_game(mut exit: EventWriter<AppExit>) {
+    exit.send(AppExit::Success);
 }
 
 #[derive(Resource)]
@@ -67,30 +129,10 @@ struct MapInfo {
     map_height: f32,
 }
 
-#[derive(Bundle)]
-struct Player {
-    position: Position,
-    sprite: SpriteBundle,
-    speed: Speed,
-}
-
-#[derive(Component)]
-struct Position {
-    position: Vec<f32>,
-}
-
-#[derive(Component)]
-struct Speed {
-    speed: i32,
-}
-
-// #[derive(Component)]
-struct MyCameraMarker;
-
 fn main() {
     // Create a new application.
-    App::default()
-    .add_plugins(DefaultPlugins
+    let mut app = App::default();
+    app.add_plugins(DefaultPlugins
         .set(WindowPlugin {
             primary_window: Some(Window {
                 title: "It's Just Business".into(),
@@ -104,61 +146,94 @@ fn main() {
     .add_plugins((
         bevy_tweening::TweeningPlugin,
         TilemapPlugin,
-        MenuPlugin,
         SpritesheetAnimationPlugin,
         TiledMapPlugin::default(),
         TextPopupPlugin,
-        GameAudioPlugin,
         AudioPlugin,
     ))
-    //.add_plugins(EguiPlugin)
-    .init_state::<GameState>()
-    .add_event::<ButtonPressTriggered>()
-    .insert_resource(MapInfo {
-        map_width: 30.0,
-        map_height: 20.0,
-    })
+    .add_plugins((
+        SettingsPlugin,
+        // After the settings it may override, before anything seeded from it.
+        ReplayPlugin,
+        MenuPlugin,
+        GameAudioPlugin,
+        MusicPlugin,
+        GamePlugin,
+        DebugOverlayPlugin,
+        MapHotReloadPlugin,
+    ))
     .add_systems(Startup, (
-        spawn_entity,
-        setup,
         spawn_camera,
         scale_tilemap_to_screen,
-        welcome_setup,
         //game_ui,
-    ))    
-    //.add_systems(Update, ui_example_system)
-    .add_systems(
-        Update,
-        (
-            keyboard_input,
-            play_button_press,
-            handle_next_popup.run_if(in_state(GameState::Playing)),
-            //game_ui.run_if(in_state(GameState::Playing)),
-            update_time.run_if(in_state(GameState::Playing)),
-            
-        )
-    )
-    .add_systems(OnEnter(GameState::Playing), game_ui)
-    .add_systems(OnEnter(GameState::Playing), update_time)
-    .run();
-    
-}
+    ));
 
-fn ui_example_system(mut contexts: EguiContexts) {
-    egui::Window::new("Hello").show(contexts.ctx_mut(), |ui| {
-        ui.label("world");
-    });
+    #[cfg(feature = "dev")]
+    app.add_plugins(dev::DevToolsPlugin);
+
+    app.run();
 }
 
+// The night itself: states, Cliff, the clock and every gameplay plugin. Nothing in here
+// needs a window or an audio device, so the tests build it on MinimalPlugins.
+struct GamePlugin;
+
+impl Plugin for GamePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins((
+            GameInputPlugin,
+            TimeScalePlugin,
+            LocalePlugin,
+            TransitionPlugin,
+            DialoguePlugin,
+            LevelPlugin,
+            MapObjectsPlugin,
+            FurniturePlugin,
+            EnergyPlugin,
+            MessPlugin,
+            NightEventsPlugin,
+            LightingPlugin,
+            NightPlugin,
+        ))
+        .init_state::<GameState>()
+        .add_computed_state::<InNight>()
+        .enable_state_scoped_entities::<InNight>()
+        .add_event::<PlayerStepped>()
+        .init_resource::<GameRng>()
+        .init_resource::<PopupState>()
+        .insert_resource(MapInfo {
+            map_width: 30.0,
+            map_height: 20.0,
+        })
+        .add_systems(Startup, build_janitor_animations)
+        .add_systems(OnEnter(InNight), (setup, spawn_entity, welcome_setup, game_ui).chain())
+        .add_systems(OnEnter(GameState::Loading), start_next_night)
+        // Movement and the clock step on the fixed simulation clock.
+        .add_systems(
+            FixedUpdate,
+            (keyboard_input, update_time).run_if(in_state(GameState::Playing)),
+        )
+        .add_systems(
+            Update,
+            (
+                handle_next_popup.run_if(in_state(GameState::Playing)),
+                //game_ui.run_if(in_state(GameState::Playing)),
+                update_tasks.run_if(in_state(GameState::Playing)),
+            )
+        );
+    }
+}
 
-// Loads tilemap and janitor sprite.
-fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
-    // Load the tilemap
+// Loads the tilemap for a new night.
+fn setup(mut commands: Commands, asset_server: Res<AssetServer>, level: Res<CurrentLevel>) {
+    // Load the tilemap for the current level
     commands.spawn((
-        TiledMapHandle(asset_server.load("tilemap_level1.tmx")),
+        TiledMapHandle(asset_server.load(level.config().map)),
         Transform::default(),
         GlobalTransform::default(),
+        StateScoped(InNight),
     ));
+    commands.insert_resource(Ledger::default());
 
     // The tilemap is 20x30 tiles, each 24x24 pixels.
     let map_tile_width = 30.0;
@@ -180,8 +255,10 @@ fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
 
 fn spawn_camera(mut commands: Commands) {
     // Spawn a 2D camera
-    let mut our_camera = Camera2dBundle::default();
-    our_camera.transform = Transform::from_xyz(350.0, 240.0, 1.0);
+    let mut our_camera = Camera2dBundle {
+        transform: Transform::from_xyz(350.0, 240.0, 1.0),
+        ..default()
+    };
     our_camera.projection.scaling_mode = ScalingMode::FixedVertical(500.0);
 
     commands.spawn(our_camera);
@@ -230,62 +307,80 @@ struct PosVar {
     last_direction: Option<Vec3>,
     
 }
+
+#[derive(Event)]
+pub struct PlayerStepped {
+    pub from: Vec3,
+    pub to: Vec3,
+}
+
+// Cliff moves one map tile per step.
+const STEP: f32 = TILE_SIZE;
+
+// Marks the stride frames of the walk clips, used for footsteps.
+pub const FOOTSTEP_MARKER: &str = "footstep";
+
+#[allow(clippy::too_many_arguments)]
 fn keyboard_input(
-    keys: Res<ButtonInput<KeyCode>>,
+    input: Res<GameInput>,
     mut local: ResMut<PosVar>,
     mut commands: Commands,
     time: Res<Time>,
     library: Res<SpritesheetLibrary>,
-    mut characters: Query<(
-        Entity,
-        &mut Transform,
-        &mut Sprite,
-        &mut SpritesheetAnimation,
-        
-    )>,
+    energy: Res<Energy>,
+    mut grid: ResMut<LevelGrid>,
+    mut furniture: Furniture,
+    mut stepped: EventWriter<PlayerStepped>,
+    mut sfx: EventWriter<PlaySfx>,
+    popup_state: Res<PopupState>,
+    mut characters: Query<&mut SpritesheetAnimation>,
 ) {
-    for (entity, mut transform, mut sprite, mut animation) in &mut characters {
-        local.timer.tick(time.delta());
-        if local.timer.just_finished() {
-            local.in_anim = false;
-        }
+    // While dialogue is open the arrows read as released, so Cliff settles into idle.
+    let held = |action| !popup_state.is_popup_active && input.pressed(action);
+
+    // One tick per simulation step, however many characters there are.
+    local.timer.tick(time.delta());
+    if local.timer.just_finished() {
+        local.in_anim = false;
+    }
+
+    for mut animation in &mut characters {
 
         if !local.in_anim {
             let mut new_animation_id = None;
             let mut direction = None;
-            let mut target_position = local.pos_vec;
 
-            if keys.pressed(KeyCode::ArrowRight) {
+            if held(GameAction::Right) {
                 new_animation_id = library.animation_with_name("rightwalk");
-                direction = Some(vec3(27., 0., 0.));
+                direction = Some(vec3(STEP, 0., 0.));
                 local.last_direction = Some(direction.unwrap());
 
-            } else if keys.pressed(KeyCode::ArrowLeft) {
+            } else if held(GameAction::Left) {
                 new_animation_id = library.animation_with_name("leftwalk");
-                direction = Some(vec3(-27., 0., 0.));
+                direction = Some(vec3(-STEP, 0., 0.));
                 local.last_direction = Some(direction.unwrap());
 
-            } else if keys.pressed(KeyCode::ArrowDown) {
+            } else if held(GameAction::Down) {
                 new_animation_id = library.animation_with_name("frontwalk");
-                direction = Some(vec3(0., -27., 0.));
+                direction = Some(vec3(0., -STEP, 0.));
                 local.last_direction = Some(direction.unwrap());
 
-            } else if keys.pressed(KeyCode::ArrowUp) {
+            } else if held(GameAction::Up) {
                 new_animation_id = library.animation_with_name("upwardwalk");
-                direction = Some(vec3(0., 27., 0.));
+                direction = Some(vec3(0., STEP, 0.));
                 local.last_direction = Some(direction.unwrap());
 
             }
-            if !keys.pressed(KeyCode::ArrowRight)
-                && !keys.pressed(KeyCode::ArrowLeft)
-                && !keys.pressed(KeyCode::ArrowDown)
-                && !keys.pressed(KeyCode::ArrowUp)
+            if !held(GameAction::Right)
+                && !held(GameAction::Left)
+                && !held(GameAction::Down)
+                && !held(GameAction::Up)
             {
                 new_animation_id = Some(match local.last_direction {
-                    Some(dir) if dir == vec3(27., 0., 0.) => library.animation_with_name("rightidle").unwrap(),
-                    Some(dir) if dir == vec3(-27., 0., 0.) => library.animation_with_name("leftidle").unwrap(),
-                    Some(dir) if dir == vec3(0., -27., 0.) => library.animation_with_name("frontidle").unwrap(),
-                    Some(dir) if dir == vec3(0., 27., 0.) => library.animation_with_name("upwardidle").unwrap(),
+                    Some(dir) if dir == vec3(STEP, 0., 0.) => library.animation_with_name("rightidle").unwrap(),
+                    Some(dir) if dir == vec3(-STEP, 0., 0.) => library.animation_with_name("leftidle").unwrap(),
+                    Some(dir) if dir == vec3(0., -STEP, 0.) => library.animation_with_name("frontidle").unwrap(),
+                    Some(dir) if dir == vec3(0., STEP, 0.) => library.animation_with_name("upwardidle").unwrap(),
                     _ => library.animation_with_name("frontidle").unwrap(),
                 });
             }
@@ -299,11 +394,29 @@ fn keyboard_input(
                 }
 
                 if let Some(dir) = direction {
-                    target_position = local.pos_vec + dir;
+                    let target_position = local.pos_vec + dir;
+                    let step_duration = energy.step_duration();
+
+                    // Stride frames last one rested step; slow them down with Cliff.
+                    animation.speed_factor =
+                        RESTED_STEP.as_secs_f32() / step_duration.as_secs_f32();
+
+                    let target_tile = world_to_tile(target_position);
+                    if grid.is_loaded()
+                        && !grid.is_walkable(target_tile)
+                        && !furniture.push(&mut commands, &mut grid, target_tile, world_to_tile(dir), step_duration)
+                    {
+                        // Walk in place against the obstacle, one bump per step.
+                        sfx.send(PlaySfx::new(SoundEffect::Bump).with_pitch_variation(0.05));
+                        local.timer.set_duration(step_duration);
+                        local.timer.reset();
+                        local.in_anim = true;
+                        continue;
+                    }
 
                     let tween = Tween::new(
                         EaseFunction::QuadraticInOut,
-                        Duration::from_millis(250),
+                        step_duration,
                         TransformPositionLens {
                             start: local.pos_vec,
                             end: target_position,
@@ -315,7 +428,13 @@ fn keyboard_input(
                         .remove::<Animator<Transform>>()
                         .insert(Animator::new(tween));
 
+                    stepped.send(PlayerStepped {
+                        from: local.pos_vec,
+                        to: target_position,
+                    });
+
                     local.pos_vec = target_position;
+                    local.timer.set_duration(step_duration);
                     local.timer.reset();
                     local.in_anim = true;
                 }
@@ -326,23 +445,19 @@ fn keyboard_input(
 
 
 
-fn spawn_entity(
+#[derive(Resource)]
+struct JanitorSheet {
+    texture: Handle<Image>,
+    layout: Handle<TextureAtlasLayout>,
+}
+
+// Builds Cliff's named animations once; each night's janitor reuses them.
+fn build_janitor_animations(
     mut commands: Commands,
      asset_server: Res<AssetServer>,
      mut library: ResMut<SpritesheetLibrary>,
      mut atlas_layouts: ResMut<Assets<TextureAtlasLayout>>,
     ) {
-    let tween = Tween::new(
-        EaseFunction::QuadraticInOut,
-        Duration::from_secs(1),
-        TransformPositionLens {
-            start: Vec3::new(360.0, 410.0, 1.0),
-            end: Vec3::new(360.0, 410.0, 1.0),
-        },
-    )
-    .with_repeat_count(RepeatCount::Finite(2))
-    .with_repeat_strategy(RepeatStrategy::MirroredRepeat);
-
     let janitor_texture: Handle<Image> = asset_server.load("janitor_spritesheet.png");
 
     let layout = atlas_layouts.add(TextureAtlasLayout::from_grid(
@@ -396,10 +511,20 @@ fn spawn_entity(
             });
     library.name_animation(upwardidle_anim_id, "upwardidle").unwrap();      
 
+    // One stride frame per step, so the feet land with the movement tween.
+    let stride_duration = AnimationDuration::PerFrame(RESTED_STEP.as_millis() as u32);
+
+    // Every stride frame is a foot coming down; audio plays a step on it.
+    let footstep = library.new_marker();
+    library.name_marker(footstep, FOOTSTEP_MARKER).unwrap();
+
     //Left walking direction
     let leftwalk_clip_id = library.new_clip(|clip| {
         //clip.push_frame_indices(Spritesheet::new(3, 4).horizontal_strip(1, 3, 3));
-        clip.push_frame_indices(Spritesheet::new(3, 4).row_partial(2, 1..=2));
+        clip.push_frame_indices(Spritesheet::new(3, 4).row_partial(2, 1..=2))
+            .set_default_duration(stride_duration)
+            .add_marker(footstep, 0)
+            .add_marker(footstep, 1);
 
     });
     let leftwalk_anim_id = library.new_animation(|animation| {
@@ -412,7 +537,10 @@ fn spawn_entity(
 
     //Right walking direction
     let rightwalk_clip_id = library.new_clip(|clip| {
-        clip.push_frame_indices(Spritesheet::new(3, 4).row_partial(3, 1..=2));
+        clip.push_frame_indices(Spritesheet::new(3, 4).row_partial(3, 1..=2))
+            .set_default_duration(stride_duration)
+            .add_marker(footstep, 0)
+            .add_marker(footstep, 1);
 
     });
     let rightwalk_anim_id = library.new_animation(|animation| {
@@ -424,7 +552,10 @@ fn spawn_entity(
 
     //Front walking direction
     let frontwalk_clip_id = library.new_clip(|clip| {
-        clip.push_frame_indices(Spritesheet::new(3, 4).row_partial(0, 1..=2));
+        clip.push_frame_indices(Spritesheet::new(3, 4).row_partial(0, 1..=2))
+            .set_default_duration(stride_duration)
+            .add_marker(footstep, 0)
+            .add_marker(footstep, 1);
     });
     let frontwalk_anim_id = library.new_animation(|animation| {
         animation
@@ -436,7 +567,10 @@ fn spawn_entity(
 
     //Upward walking direction
     let upwardwalk_clip_id = library.new_clip(|clip| {
-        clip.push_frame_indices(Spritesheet::new(3, 4).row_partial(1, 1..=2));
+        clip.push_frame_indices(Spritesheet::new(3, 4).row_partial(1, 1..=2))
+            .set_default_duration(stride_duration)
+            .add_marker(footstep, 0)
+            .add_marker(footstep, 1);
 
     });
     let upwardwalk_anim_id = library.new_animation(|animation| {
@@ -447,7 +581,30 @@ fn spawn_entity(
         });
     library.name_animation(upwardwalk_anim_id, "upwardwalk").unwrap();
 
- 
+    commands.insert_resource(JanitorSheet {
+        texture: janitor_texture,
+        layout,
+    });
+}
+
+fn spawn_entity(
+    mut commands: Commands,
+    sheet: Res<JanitorSheet>,
+    library: Res<SpritesheetLibrary>,
+) {
+    let tween = Tween::new(
+        EaseFunction::QuadraticInOut,
+        Duration::from_secs(1),
+        TransformPositionLens {
+            start: Vec3::new(360.0, 408.0, 1.0),
+            end: Vec3::new(360.0, 408.0, 1.0),
+        },
+    )
+    .with_repeat_count(RepeatCount::Finite(2))
+    .with_repeat_strategy(RepeatStrategy::MirroredRepeat);
+
+    let frontidle_anim_id = library.animation_with_name("frontidle").unwrap();
+
     let id = commands
         .spawn((
             SpriteBundle {
@@ -456,37 +613,28 @@ fn spawn_entity(
                     custom_size: Some(Vec2::new(21., 32.)),
                     ..default()
                 },
-                texture: janitor_texture,
+                texture: sheet.texture.clone(),
                 transform: Transform {
-                    translation: Vec3::new(360.0, 410.0, 1.0),
+                    translation: Vec3::new(360.0, 408.0, 1.0),
                     ..Default::default()
                 },
                 ..default()
             },
             TextureAtlas {
-                layout,
+                layout: sheet.layout.clone(),
                 ..default()
             },
             SpritesheetAnimation::from_id(frontidle_anim_id),
             Animator::new(tween),
+            StateScoped(InNight),
 
         ))
         .id();
     commands.insert_resource(PosVar {
         in_anim: false,
-        pos_vec: Vec3::new(360., 410., 1.),
-        id: id,
+        pos_vec: Vec3::new(360., 408., 1.),
+        id,
         timer: Timer::from_seconds(0.25, TimerMode::Once),
         last_direction: None,
     });
 }
-
-fn spawn_task(mut commands: Commands, asset_server: Res<AssetServer>) {
-    let planttexture: Handle<Image> = asset_server.load("plant_asset1.png");
-    let mut plant_object = SpriteBundle::default();
-
-    plant_object.texture = planttexture;
-
-    plant_object.transform = Transform::from_xyz(100.0, 410.0, 1.0);
-    commands.spawn(plant_object);
-}
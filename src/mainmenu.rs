@@ -1,4 +1,14 @@
+use bevy::ecs::system::SystemParam;
 use bevy::prelude::*;
+use std::sync::Arc;
+use crate::audio::{AudioBus, AudioMixer, CycleVolume, PlaySfx, SoundEffect, ToggleMute};
+use crate::input::{GameAction, GameInput, MenuPointer, PointerInputSet};
+use crate::locale::{CycleLanguage, Locale};
+use crate::night::{AdvanceLevel, CurrentLevel, NightOutcome, NightReport};
+use crate::settings::UserSettings;
+use crate::text::no_modal_open;
+use crate::time_scale::CycleGameSpeed;
+use crate::transition::{transition_idle, TransitionTo};
 use crate::{despawn_state, quit_game, GameState, RootEntity};
 
 mod constants {
@@ -8,20 +18,92 @@ mod constants {
         pub const NORMAL: Color = Color::srgb(0.15, 0.15, 0.15);
         pub const HOVERED: Color = Color::srgb(0.25, 0.25, 0.25);
         pub const PRESSED: Color = Color::srgb(0.35, 0.35, 0.35);
+        pub const DISABLED: Color = Color::srgb(0.5, 0.5, 0.5);
     }
 }
 
 use constants::*;
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum MenuId {
+    Main,
+    Settings,
+    Audio,
+    Pause,
+    Results,
+}
+
+// What a menu button does when it is activated.
+#[derive(Clone)]
+enum MenuAction {
+    State(GameState),
+    Submenu(MenuId),
+    Back,
+    Send(Arc<dyn Fn(&mut World) + Send + Sync>),
+}
+
+impl MenuAction {
+    // Sends a typed event for any plugin to handle.
+    fn send<E: Event + Clone>(event: E) -> Self {
+        MenuAction::Send(Arc::new(move |world: &mut World| {
+            world.send_event(event.clone());
+        }))
+    }
+}
+
+impl From<GameState> for MenuAction {
+    fn from(state: GameState) -> Self {
+        MenuAction::State(state)
+    }
+}
+
+impl From<MenuId> for MenuAction {
+    fn from(menu: MenuId) -> Self {
+        MenuAction::Submenu(menu)
+    }
+}
+
 struct MenuButton {
     text: String,
-    action: GameState,
+    action: MenuAction,
     style: Option<Style>,
     enabled: bool,
 }
 
 #[derive(Component)]
-struct ButtonAction(GameState);
+struct ButtonAction(MenuAction);
+
+// Disabled buttons are drawn greyed out and ignore every kind of input.
+#[derive(Component)]
+struct Disabled;
+
+// Position of a button in its menu, top to bottom.
+#[derive(Component)]
+struct MenuIndex(usize);
+
+#[derive(Component)]
+struct MenuRoot {
+    back: Option<GameState>,
+}
+
+// The button that keyboard and gamepad input acts on.
+#[derive(Resource, Default)]
+struct MenuFocus {
+    index: usize,
+}
+
+// Open menus, innermost last. Back pops one level before leaving the state.
+#[derive(Resource, Default)]
+struct MenuStack {
+    menus: Vec<MenuId>,
+    rebuild: bool,
+}
+
+impl MenuStack {
+    fn top(&self) -> Option<MenuId> {
+        self.menus.last().copied()
+    }
+}
 
 #[derive(Default)]
 struct MenuBuilder {
@@ -29,7 +111,9 @@ struct MenuBuilder {
     background_color: Option<Color>,
     buttons: Vec<MenuButton>,
     title: Option<String>,
+    labels: Vec<String>,
     spacing: f32,
+    back: Option<GameState>,
 }
 
 impl MenuBuilder {
@@ -57,51 +141,63 @@ impl MenuBuilder {
         self
     }
 
+    // A line of plain text under the title, above the buttons.
+    fn add_label(mut self, text: impl Into<String>) -> Self {
+        self.labels.push(text.into());
+        self
+    }
+
     fn with_spacing(mut self, spacing: f32) -> Self {
         self.spacing = spacing;
         self
     }
 
+    // Where Escape or B goes from this menu once there is no submenu to close.
+    fn with_back(mut self, action: GameState) -> Self {
+        self.back = Some(action);
+        self
+    }
+
     fn add_button(
         mut self,
         text: impl Into<String>,
-        action: GameState,
+        action: impl Into<MenuAction>,
         enabled: bool,
     ) -> Self {
         self.buttons.push(MenuButton {
             text: text.into(),
-            action,
+            action: action.into(),
             style: None,
             enabled,
         });
         self
     }
 
-    fn add_styled_button(
-        mut self,
+    fn add_event_button<E: Event + Clone>(
+        self,
         text: impl Into<String>,
-        action: GameState,
-        style: Style,
+        event: E,
         enabled: bool,
     ) -> Self {
-        self.buttons.push(MenuButton {
-            text: text.into(),
-            action,
-            style: Some(style),
-            enabled,
-        });
-        self
+        self.add_button(text, MenuAction::send(event), enabled)
+    }
+
+    fn add_back_button(self, text: impl Into<String>) -> Self {
+        self.add_button(text, MenuAction::Back, true)
     }
 
     fn build(self, commands: &mut Commands) -> Entity {
         let root = commands
-            .spawn(NodeBundle {
-                style: self.style,
-                background_color: self.background_color.map(|c| c.into()).unwrap_or_default(),
-                ..default()
-            })
+            .spawn((
+                NodeBundle {
+                    style: self.style,
+                    background_color: self.background_color.map(|c| c.into()).unwrap_or_default(),
+                    ..default()
+                },
+                MenuRoot { back: self.back },
+            ))
             .with_children(|parent| {
-                if let Some(title) = self.title {
+                if let Some(title) = self.title.clone() {
                     parent.spawn(TextBundle::from_section(
                         title,
                         TextStyle {
@@ -110,7 +206,20 @@ impl MenuBuilder {
                             ..default()
                         }
                     ));
+                }
+
+                for label in self.labels {
+                    parent.spawn(TextBundle::from_section(
+                        label,
+                        TextStyle {
+                            font_size: 28.,
+                            color: Color::WHITE,
+                            ..default()
+                        }
+                    ));
+                }
 
+                if self.title.is_some() {
                     parent.spawn(NodeBundle {
                         style: Style {
                             height: Val::Px(self.spacing),
@@ -120,24 +229,25 @@ impl MenuBuilder {
                     });
                 }
 
-                for button in self.buttons {
+                for (index, button) in self.buttons.into_iter().enumerate() {
                     let button_style = button.style.unwrap_or(Style {
-                        width: Val::Px(200.),
+                        width: Val::Px(260.),
                         height: Val::Px(50.),
                         justify_content: JustifyContent::Center,
                         align_items: AlignItems::Center,
                         margin: UiRect::all(Val::Px(5.)),
                         ..default()
                     });
-                    parent.spawn(ButtonBundle {
+                    let mut entity = parent.spawn(ButtonBundle {
                         style: button_style,
                         background_color: if button.enabled {
-                            Color::srgb(0.25, 0.25, 0.25).into()
+                            menu::NORMAL.into()
                         } else {
-                            Color::srgb(0.5, 0.5, 0.5).into()
+                            menu::DISABLED.into()
                         },
                         ..default()
-                    })
+                    });
+                    entity
                         .with_children(|parent| {
                             parent.spawn(TextBundle::from_section(
                                 button.text,
@@ -146,13 +256,16 @@ impl MenuBuilder {
                                     color: if button.enabled {
                                         Color::WHITE
                                     } else {
-                                        Color::srgb(0.5, 0.5, 0.5)
+                                        menu::DISABLED
                                     },
                                     ..default()
                                 }
                             ));
                         })
-                        .insert(ButtonAction(button.action));
+                        .insert((ButtonAction(button.action), MenuIndex(index)));
+                    if !button.enabled {
+                        entity.insert(Disabled);
+                    }
                 }
             })
             .id();
@@ -160,47 +273,362 @@ impl MenuBuilder {
     }
 }
 
-fn spawn_main_menu(mut commands: Commands) {
-    let entity = MenuBuilder::new()
-        .with_title("It's Just Business")
-        .with_spacing(20.)
-        .with_background(Color::srgb(0., 0., 0.,))
-        .add_button("Play", GameState::Playing, true)
-        //.add_button("Settings", GameState::Settings, true)
-        .add_button("Exit", GameState::Exit, true)
-        .build(&mut commands);
+// Everything the menus read their labels from.
+#[derive(SystemParam)]
+struct MenuContent<'w> {
+    mixer: Res<'w, AudioMixer>,
+    locale: Res<'w, Locale>,
+    level: Res<'w, CurrentLevel>,
+    time: Res<'w, Time<Virtual>>,
+    report: Option<Res<'w, NightReport>>,
+}
+
+fn build_menu(menu: MenuId, commands: &mut Commands, content: &MenuContent) -> Entity {
+    let (mixer, locale) = (&content.mixer, &content.locale);
+    match menu {
+        MenuId::Main => MenuBuilder::new()
+            .with_title(locale.get("game-title"))
+            .with_spacing(20.)
+            .with_background(Color::srgb(0., 0., 0.,))
+            .add_button(locale.get("menu-play"), GameState::Playing, true)
+            .add_button(locale.get("menu-settings"), GameState::Settings, true)
+            .add_button(locale.get("menu-exit"), GameState::Exit, true)
+            .build(commands),
+        MenuId::Settings => MenuBuilder::new()
+            .with_title(locale.get("menu-settings"))
+            .with_spacing(20.)
+            .with_background(Color::srgb(0., 0., 0.))
+            .add_button(locale.get("menu-audio"), MenuId::Audio, true)
+            .add_event_button(locale.get("menu-language"), CycleLanguage, true)
+            .add_event_button(
+                locale.format("menu-game-speed", &[("speed", &content.time.relative_speed())]),
+                CycleGameSpeed,
+                true,
+            )
+            .add_button(locale.get("menu-back"), GameState::Menu, true)
+            .with_back(GameState::Menu)
+            .build(commands),
+        MenuId::Audio => {
+            let label = |key, bus| {
+                let level = format!("{:.0}", mixer.bus(bus) * 100.0);
+                locale.format(key, &[("level", &level)])
+            };
+            MenuBuilder::new()
+                .with_title(locale.get("menu-audio"))
+                .with_spacing(20.)
+                .with_background(Color::srgb(0., 0., 0.))
+                .add_event_button(label("audio-master", AudioBus::Master), CycleVolume(AudioBus::Master), true)
+                .add_event_button(label("audio-music", AudioBus::Music), CycleVolume(AudioBus::Music), !mixer.muted)
+                .add_event_button(label("audio-effects", AudioBus::Sfx), CycleVolume(AudioBus::Sfx), !mixer.muted)
+                .add_event_button(label("audio-menus", AudioBus::Ui), CycleVolume(AudioBus::Ui), !mixer.muted)
+                .add_event_button(locale.get(if mixer.muted { "audio-unmute" } else { "audio-mute" }), ToggleMute, true)
+                .add_back_button(locale.get("menu-back"))
+                .build(commands)
+        }
+        // Drawn over the paused night, which stays in place underneath.
+        MenuId::Pause => MenuBuilder::new()
+            .with_title(locale.get("pause-title"))
+            .with_spacing(20.)
+            .with_background(Color::BLACK.with_alpha(0.6))
+            .add_button(locale.get("menu-resume"), GameState::Playing, true)
+            .add_button(locale.get("menu-restart"), GameState::Loading, true)
+            .add_button(locale.get("menu-title"), GameState::Menu, true)
+            .with_back(GameState::Playing)
+            .build(commands),
+        MenuId::Results => {
+            let Some(report) = content.report.as_deref() else {
+                warn!("Results menu opened without a night report");
+                return MenuBuilder::new()
+                    .add_button(locale.get("menu-title"), GameState::Menu, true)
+                    .build(commands);
+            };
+            let title = match report.outcome {
+                NightOutcome::Finished => "results-finished",
+                NightOutcome::ShiftOver => "results-shift-over",
+                NightOutcome::Exhausted => "results-exhausted",
+            };
+            let hours = report.minutes_used / 60;
+            let minutes = format!("{:02}", report.minutes_used % 60);
+            MenuBuilder::new()
+                .with_title(locale.get(title))
+                .with_spacing(20.)
+                .with_background(Color::srgb(0., 0., 0.))
+                .add_label(locale.format(
+                    "results-tasks",
+                    &[("count", &report.tasks_completed), ("total", &report.tasks_total)],
+                ))
+                .add_label(locale.format("results-time", &[("hours", &hours), ("minutes", &minutes)]))
+                .add_label(locale.format("results-earnings", &[("amount", &report.earnings)]))
+                .add_label(locale.format("results-damages", &[("amount", &report.damages)]))
+                .add_label(locale.format("results-grade", &[("grade", &report.grade.letter())]))
+                .add_button(locale.get("menu-retry"), GameState::Loading, true)
+                .add_event_button(
+                    locale.get("menu-next-level"),
+                    AdvanceLevel,
+                    report.grade.is_pass() && content.level.has_next(),
+                )
+                .add_button(locale.get("menu-title"), GameState::Menu, true)
+                .with_back(GameState::Menu)
+                .build(commands)
+        }
+    }
+}
+
+fn open_root_menu(
+    menu: MenuId,
+    commands: &mut Commands,
+    stack: &mut MenuStack,
+    focus: &mut MenuFocus,
+    content: &MenuContent,
+) {
+    stack.menus = vec![menu];
+    stack.rebuild = false;
+    focus.index = 0;
+    let entity = build_menu(menu, commands, content);
     commands.insert_resource(RootEntity(entity));
 }
 
-fn spawn_settings_menu(mut commands: Commands) {
-    let entity = MenuBuilder::new()
-        .with_title("Settings")
-        .with_spacing(20.)
-        .with_background(Color::srgb(0., 0., 0.))
-        .add_button("Back", GameState::Menu, true)
-        .build(&mut commands);
+fn spawn_main_menu(
+    mut commands: Commands,
+    mut stack: ResMut<MenuStack>,
+    mut focus: ResMut<MenuFocus>,
+    content: MenuContent,
+) {
+    open_root_menu(MenuId::Main, &mut commands, &mut stack, &mut focus, &content);
+}
+
+fn spawn_settings_menu(
+    mut commands: Commands,
+    mut stack: ResMut<MenuStack>,
+    mut focus: ResMut<MenuFocus>,
+    content: MenuContent,
+) {
+    open_root_menu(MenuId::Settings, &mut commands, &mut stack, &mut focus, &content);
+}
+
+fn spawn_pause_menu(
+    mut commands: Commands,
+    mut stack: ResMut<MenuStack>,
+    mut focus: ResMut<MenuFocus>,
+    content: MenuContent,
+) {
+    open_root_menu(MenuId::Pause, &mut commands, &mut stack, &mut focus, &content);
+}
+
+fn spawn_results_menu(
+    mut commands: Commands,
+    mut stack: ResMut<MenuStack>,
+    mut focus: ResMut<MenuFocus>,
+    content: MenuContent,
+) {
+    open_root_menu(MenuId::Results, &mut commands, &mut stack, &mut focus, &content);
+}
+
+fn pause_game(input: Res<GameInput>, mut transitions: EventWriter<TransitionTo>) {
+    if input.just_pressed(GameAction::Back) {
+        transitions.send(TransitionTo::new(GameState::Paused));
+    }
+}
+
+// Swaps in the top of the stack after a submenu opens or closes, and refreshes
+// labels when the audio levels, the language or the game speed change.
+fn rebuild_menu(
+    mut commands: Commands,
+    mut stack: ResMut<MenuStack>,
+    content: MenuContent,
+    settings: Res<UserSettings>,
+    root: Option<Res<RootEntity>>,
+) {
+    // Picks up a new game speed, which lives on the virtual clock.
+    let speed_changed = settings.is_changed() && !settings.is_added();
+    let refresh = (content.mixer.is_changed() && stack.top() == Some(MenuId::Audio))
+        || (content.locale.is_changed() && !content.locale.is_added())
+        || (speed_changed && stack.top() == Some(MenuId::Settings));
+    if !stack.rebuild && !refresh {
+        return;
+    }
+    stack.rebuild = false;
+
+    let Some(menu) = stack.top() else {
+        return;
+    };
+    if let Some(root) = root {
+        if let Some(entity) = commands.get_entity(root.0) {
+            entity.despawn_recursive();
+        }
+    }
+    let entity = build_menu(menu, &mut commands, &content);
     commands.insert_resource(RootEntity(entity));
 }
 
-fn update_menu(
-    mut interaction_query: Query<
-        (&ButtonAction, &Interaction, &mut BackgroundColor),
-        (Changed<Interaction>, With<Button>),
-    >,
-    mut game_state: ResMut<NextState<GameState>>,
+fn run_action(
+    action: &MenuAction,
+    commands: &mut Commands,
+    stack: &mut MenuStack,
+    focus: &mut MenuFocus,
+    back: Option<GameState>,
+    transitions: &mut EventWriter<TransitionTo>,
 ) {
-    for (action, interaction, mut color) in &mut interaction_query {
+    match action {
+        MenuAction::State(state) => {
+            transitions.send(TransitionTo::new(state.clone()));
+        }
+        MenuAction::Submenu(menu) => {
+            stack.menus.push(*menu);
+            stack.rebuild = true;
+            focus.index = 0;
+        }
+        MenuAction::Back => {
+            go_back(stack, focus, back, transitions);
+        }
+        MenuAction::Send(send) => {
+            let send = send.clone();
+            commands.add(move |world: &mut World| send(world));
+        }
+    }
+}
+
+// False when there is nothing to go back to.
+fn go_back(
+    stack: &mut MenuStack,
+    focus: &mut MenuFocus,
+    back: Option<GameState>,
+    transitions: &mut EventWriter<TransitionTo>,
+) -> bool {
+    if stack.menus.len() > 1 {
+        stack.menus.pop();
+        stack.rebuild = true;
+        focus.index = 0;
+        true
+    } else if let Some(back) = back {
+        transitions.send(TransitionTo::new(back));
+        true
+    } else {
+        false
+    }
+}
+
+// Hands the pointer's hovers and clicks to GameInput, so replays see them too.
+#[allow(clippy::type_complexity)]
+fn menu_pointer_input(
+    buttons: Query<(&MenuIndex, &Interaction), (Changed<Interaction>, With<Button>, Without<Disabled>)>,
+    mut input: ResMut<GameInput>,
+) {
+    for (index, interaction) in &buttons {
         match *interaction {
-            Interaction::Pressed => {
-                *color = menu::PRESSED.into();
-                game_state.set(action.0.clone());
-            },
-            Interaction::Hovered => *color = menu::HOVERED.into(),
-            Interaction::None => *color = menu::NORMAL.into(),
+            Interaction::Pressed => input.point_at_menu(MenuPointer::Click(index.0)),
+            Interaction::Hovered => input.point_at_menu(MenuPointer::Hover(index.0)),
+            Interaction::None => {}
         }
     }
 }
 
+#[allow(clippy::too_many_arguments)]
+fn update_menu(
+    mut commands: Commands,
+    input: Res<GameInput>,
+    buttons: Query<(&ButtonAction, &MenuIndex), Without<Disabled>>,
+    roots: Query<&MenuRoot>,
+    mut stack: ResMut<MenuStack>,
+    mut focus: ResMut<MenuFocus>,
+    mut transitions: EventWriter<TransitionTo>,
+    mut sfx: EventWriter<PlaySfx>,
+) {
+    let back = roots.iter().find_map(|root| root.back.clone());
+
+    match input.menu_pointer() {
+        Some(MenuPointer::Click(pointed)) => {
+            let Some((action, index)) = buttons.iter().find(|(_, index)| index.0 == pointed) else {
+                return;
+            };
+            focus.index = index.0;
+            sfx.send(PlaySfx::new(SoundEffect::MenuIn));
+            run_action(&action.0, &mut commands, &mut stack, &mut focus, back, &mut transitions);
+        }
+        // The mouse and the keyboard share one focus.
+        Some(MenuPointer::Hover(pointed)) if buttons.iter().any(|(_, index)| index.0 == pointed) => {
+            focus.index = pointed;
+        }
+        _ => {}
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn navigate_menu(
+    mut commands: Commands,
+    input: Res<GameInput>,
+    buttons: Query<(&ButtonAction, &MenuIndex, Has<Disabled>)>,
+    roots: Query<&MenuRoot>,
+    mut stack: ResMut<MenuStack>,
+    mut focus: ResMut<MenuFocus>,
+    mut transitions: EventWriter<TransitionTo>,
+    mut sfx: EventWriter<PlaySfx>,
+) {
+    let up = input.just_pressed(GameAction::Up);
+    let down = input.just_pressed(GameAction::Down);
+    let activate = input.just_pressed(GameAction::Confirm);
+    let back_pressed = input.just_pressed(GameAction::Back);
+    let back = roots.iter().find_map(|root| root.back.clone());
+
+    if back_pressed {
+        if go_back(&mut stack, &mut focus, back, &mut transitions) {
+            sfx.send(PlaySfx::new(SoundEffect::MenuOut));
+        }
+        return;
+    }
+
+    // Focus only ever lands on buttons that can be used.
+    let mut enabled: Vec<usize> = buttons
+        .iter()
+        .filter(|(_, _, disabled)| !disabled)
+        .map(|(_, index, _)| index.0)
+        .collect();
+    enabled.sort_unstable();
+    if enabled.is_empty() {
+        return;
+    }
+
+    if up || down {
+        let position = enabled.iter().position(|index| *index == focus.index).unwrap_or(0);
+        let count = enabled.len();
+        let next = if up {
+            (position + count - 1) % count
+        } else {
+            (position + 1) % count
+        };
+        focus.index = enabled[next];
+        sfx.send(PlaySfx::new(SoundEffect::Click));
+    } else if !enabled.contains(&focus.index) {
+        focus.index = enabled[0];
+    }
+
+    if activate {
+        if let Some((action, _, _)) = buttons
+            .iter()
+            .find(|(_, index, disabled)| index.0 == focus.index && !disabled)
+        {
+            sfx.send(PlaySfx::new(SoundEffect::MenuIn));
+            run_action(&action.0, &mut commands, &mut stack, &mut focus, back, &mut transitions);
+        }
+    }
+}
+
+fn paint_menu_buttons(
+    focus: Res<MenuFocus>,
+    mut buttons: Query<(&MenuIndex, &Interaction, &mut BackgroundColor, Has<Disabled>), With<Button>>,
+) {
+    for (index, interaction, mut color, disabled) in &mut buttons {
+        let focused = index.0 == focus.index;
+        *color = match *interaction {
+            _ if disabled => menu::DISABLED.into(),
+            Interaction::Pressed => menu::PRESSED.into(),
+            Interaction::Hovered => menu::HOVERED.into(),
+            Interaction::None if focused => menu::HOVERED.into(),
+            Interaction::None => menu::NORMAL.into(),
+        };
+    }
+}
+
 pub struct MenuPlugin;
 
 #[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
@@ -211,12 +639,35 @@ impl Plugin for MenuPlugin {
         app.configure_sets(Update, MenuUpdateSet.run_if(
             in_state(GameState::Menu)
                 .or_else(in_state(GameState::Settings))
+                .or_else(in_state(GameState::Paused))
+                .or_else(in_state(GameState::Results))
+                .and_then(transition_idle)
         ));
         app.add_systems(OnEnter(GameState::Menu), spawn_main_menu);
         app.add_systems(OnExit(GameState::Menu), despawn_state);
         app.add_systems(OnEnter(GameState::Exit), quit_game);
         app.add_systems(OnEnter(GameState::Settings), spawn_settings_menu);
         app.add_systems(OnExit(GameState::Settings), despawn_state);
-        app.add_systems(Update, update_menu.in_set(MenuUpdateSet));
+        app.add_systems(OnEnter(GameState::Paused), spawn_pause_menu);
+        app.add_systems(OnExit(GameState::Paused), despawn_state);
+        app.add_systems(OnEnter(GameState::Results), spawn_results_menu);
+        app.add_systems(OnExit(GameState::Results), despawn_state);
+        app.init_resource::<MenuFocus>();
+        app.init_resource::<MenuStack>();
+        app.add_systems(PreUpdate, menu_pointer_input.in_set(PointerInputSet));
+        app.add_systems(
+            Update,
+            (rebuild_menu, update_menu, navigate_menu, paint_menu_buttons)
+                .chain()
+                .in_set(MenuUpdateSet),
+        );
+        app.add_systems(
+            Update,
+            pause_game.run_if(
+                in_state(GameState::Playing)
+                    .and_then(no_modal_open)
+                    .and_then(transition_idle),
+            ),
+        );
     }
 }
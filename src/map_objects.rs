@@ -0,0 +1,429 @@
+use bevy::prelude::*;
+use bevy::utils::{HashMap, HashSet};
+use bevy_ecs_tiled::prelude::{TiledMap, TiledMapHandle};
+use tiled::{LayerType, ObjectShape, Properties, PropertyValue};
+
+use crate::audio::{PlaySfx, SoundEffect};
+use crate::input::{GameAction, GameInput, InteractSet};
+use crate::level::{
+    tile_to_world, tiled_to_world, world_to_tile, LevelGrid, LevelGridSet, MapReloaded, TILE_SIZE,
+};
+use crate::locale::Locale;
+use crate::text::{no_modal_open, PopupQueue};
+use crate::{GameState, InNight, PlayerStepped, PosVar};
+
+// Gives Tiled objects their meaning: each object's class picks a constructor that reads
+// its custom properties into components. Plugins register the classes they own with
+// `app.register_map_object`; objects without a class (obstacles, rooms) are left alone.
+pub struct MapObjectsPlugin;
+
+const GUARD_Z: f32 = 1.2;
+const DOOR_RANGE: i32 = 1;
+
+// Added to every entity spawned from a map object, so a reload can replace them all.
+#[derive(Component)]
+pub struct MapObject {
+    pub tile: IVec2,
+}
+
+// Spawning the current map's objects, once its grid is built. Systems that block the
+// tiles of new objects run after this to see them the frame they arrive.
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct MapObjectSet;
+
+type Constructor = Box<dyn Fn(&ObjectProperties, &mut Commands) -> Result<Entity, String> + Send + Sync>;
+
+#[derive(Resource, Default)]
+pub struct MapObjectRegistry {
+    constructors: HashMap<String, Constructor>,
+    // Classes some other system reads straight from the map.
+    ignored: HashSet<String>,
+}
+
+pub trait RegisterMapObject {
+    fn register_map_object<B: Bundle>(
+        &mut self,
+        class: &str,
+        build: impl Fn(&ObjectProperties) -> Result<B, String> + Send + Sync + 'static,
+    ) -> &mut Self;
+
+    fn ignore_map_object(&mut self, class: &str) -> &mut Self;
+}
+
+impl RegisterMapObject for App {
+    fn register_map_object<B: Bundle>(
+        &mut self,
+        class: &str,
+        build: impl Fn(&ObjectProperties) -> Result<B, String> + Send + Sync + 'static,
+    ) -> &mut Self {
+        let mut registry = self.world_mut().get_resource_or_insert_with(MapObjectRegistry::default);
+        let constructor: Constructor = Box::new(move |properties, commands| Ok(commands.spawn(build(properties)?).id()));
+        if registry.constructors.insert(class.to_string(), constructor).is_some() {
+            warn!("Map object class '{}' was registered twice", class);
+        }
+        self
+    }
+
+    fn ignore_map_object(&mut self, class: &str) -> &mut Self {
+        let mut registry = self.world_mut().get_resource_or_insert_with(MapObjectRegistry::default);
+        registry.ignored.insert(class.to_string());
+        self
+    }
+}
+
+// A map object's custom properties, read with the type each field expects.
+pub struct ObjectProperties<'a> {
+    properties: &'a Properties,
+}
+
+impl<'a> ObjectProperties<'a> {
+    pub fn new(properties: &'a Properties) -> Self {
+        Self { properties }
+    }
+
+    pub fn get<T: FromProperty>(&self, name: &str) -> Result<Option<T>, String> {
+        match self.properties.get(name) {
+            Some(value) => T::from_property(value)
+                .map(Some)
+                .ok_or_else(|| format!("property '{}' should be {}, found {:?}", name, T::EXPECTED, value)),
+            None => Ok(None),
+        }
+    }
+
+    pub fn get_or<T: FromProperty>(&self, name: &str, default: T) -> Result<T, String> {
+        Ok(self.get(name)?.unwrap_or(default))
+    }
+
+    pub fn require<T: FromProperty>(&self, name: &str) -> Result<T, String> {
+        self.get(name)?.ok_or_else(|| format!("missing property '{}'", name))
+    }
+}
+
+pub trait FromProperty: Sized {
+    // Shown in warnings when a property has the wrong type.
+    const EXPECTED: &'static str;
+
+    fn from_property(value: &PropertyValue) -> Option<Self>;
+}
+
+impl FromProperty for i32 {
+    const EXPECTED: &'static str = "an int";
+
+    fn from_property(value: &PropertyValue) -> Option<Self> {
+        match value {
+            PropertyValue::IntValue(value) => Some(*value),
+            _ => None,
+        }
+    }
+}
+
+impl FromProperty for u32 {
+    const EXPECTED: &'static str = "a positive int";
+
+    fn from_property(value: &PropertyValue) -> Option<Self> {
+        match value {
+            PropertyValue::IntValue(value) => u32::try_from(*value).ok(),
+            _ => None,
+        }
+    }
+}
+
+impl FromProperty for f32 {
+    const EXPECTED: &'static str = "a float";
+
+    // Tiled writes whole numbers typed into a float field as ints.
+    fn from_property(value: &PropertyValue) -> Option<Self> {
+        match value {
+            PropertyValue::FloatValue(value) => Some(*value),
+            PropertyValue::IntValue(value) => Some(*value as f32),
+            _ => None,
+        }
+    }
+}
+
+impl FromProperty for bool {
+    const EXPECTED: &'static str = "a bool";
+
+    fn from_property(value: &PropertyValue) -> Option<Self> {
+        match value {
+            PropertyValue::BoolValue(value) => Some(*value),
+            _ => None,
+        }
+    }
+}
+
+impl FromProperty for String {
+    const EXPECTED: &'static str = "a string";
+
+    fn from_property(value: &PropertyValue) -> Option<Self> {
+        match value {
+            PropertyValue::StringValue(value) => Some(value.clone()),
+            _ => None,
+        }
+    }
+}
+
+// The map whose objects are currently spawned.
+#[derive(Resource, Default)]
+struct SpawnedMap(Option<AssetId<TiledMap>>);
+
+// Blocks its tile until Cliff opens it; locked doors stay shut.
+#[derive(Component)]
+pub struct Door {
+    pub open: bool,
+    pub locked: bool,
+}
+
+// Walks back and forth along its row, `patrol` tiles out from where it was placed.
+#[derive(Component)]
+pub struct Guard {
+    pub patrol: i32,
+    // Tiles per second.
+    pub speed: f32,
+    offset: f32,
+    heading: f32,
+}
+
+impl Guard {
+    // The tile the guard is closest to, given the tile it was placed on.
+    pub fn tile(&self, start: IVec2) -> IVec2 {
+        start + IVec2::new(self.offset.round() as i32, 0)
+    }
+}
+
+// Shows a message the first time Cliff steps onto its tile, or every time unless `once`.
+#[derive(Component)]
+pub struct Trigger {
+    // A locale key, or the text itself when no key by that name exists.
+    pub message: String,
+    pub once: bool,
+}
+
+impl Plugin for MapObjectsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<MapObjectRegistry>();
+        app.init_resource::<SpawnedMap>();
+        app.register_map_object("Door", |properties| {
+            Ok(Door {
+                open: properties.get_or("open", false)?,
+                locked: properties.get_or("locked", false)?,
+            })
+        });
+        app.register_map_object("Guard", |properties| {
+            Ok((
+                Guard {
+                    patrol: properties.get_or("patrol", 2)?,
+                    speed: properties.get_or("speed", 1.0)?,
+                    offset: 0.0,
+                    heading: 1.0,
+                },
+                Sprite {
+                    color: Color::srgb(0.3, 0.35, 0.8),
+                    custom_size: Some(Vec2::new(14.0, 20.0)),
+                    ..default()
+                },
+                Handle::<Image>::default(),
+            ))
+        });
+        app.register_map_object("Trigger", |properties| {
+            Ok(Trigger {
+                message: properties.require("message")?,
+                once: properties.get_or("once", true)?,
+            })
+        });
+        app.configure_sets(Update, MapObjectSet.after(LevelGridSet));
+        app.add_systems(OnEnter(InNight), reset_map_objects);
+        app.add_systems(
+            Update,
+            (spawn_map_objects, block_closed_doors)
+                .chain()
+                .in_set(MapObjectSet)
+                .run_if(in_state(InNight)),
+        );
+        app.add_systems(
+            Update,
+            open_doors
+                .run_if(in_state(GameState::Playing).and_then(no_modal_open))
+                .in_set(InteractSet::Door),
+        );
+        app.add_systems(
+            FixedUpdate,
+            (patrol_guards, fire_triggers).run_if(in_state(GameState::Playing)),
+        );
+    }
+}
+
+fn reset_map_objects(mut spawned: ResMut<SpawnedMap>) {
+    spawned.0 = None;
+}
+
+#[allow(clippy::too_many_arguments)]
+fn spawn_map_objects(
+    mut commands: Commands,
+    mut reloaded: EventReader<MapReloaded>,
+    mut spawned: ResMut<SpawnedMap>,
+    registry: Res<MapObjectRegistry>,
+    asset_server: Res<AssetServer>,
+    maps: Res<Assets<TiledMap>>,
+    handles: Query<&TiledMapHandle>,
+    existing: Query<Entity, With<MapObject>>,
+) {
+    // A reloaded map may have moved, added or removed anything, so start over.
+    if reloaded.read().count() > 0 {
+        for entity in &existing {
+            commands.entity(entity).despawn_recursive();
+        }
+        spawned.0 = None;
+    }
+
+    for handle in &handles {
+        if spawned.0 == Some(handle.0.id()) {
+            continue;
+        }
+        let Some(tiled_map) = maps.get(&handle.0) else {
+            continue;
+        };
+        let map_name = asset_server
+            .get_path(&handle.0)
+            .map(|path| path.to_string())
+            .unwrap_or_else(|| "map".to_string());
+        let map = &tiled_map.map;
+        let map_height_px = (map.height * map.tile_height) as f32;
+
+        let mut count = 0;
+        for layer in map.layers() {
+            let LayerType::Objects(objects) = layer.layer_type() else {
+                continue;
+            };
+            for object in objects.objects() {
+                if object.user_type.is_empty() || registry.ignored.contains(&object.user_type) {
+                    continue;
+                }
+                let Some(constructor) = registry.constructors.get(&object.user_type) else {
+                    warn!(
+                        "{}: object {} has unknown class '{}'",
+                        map_name,
+                        object.id(),
+                        object.user_type
+                    );
+                    continue;
+                };
+
+                let properties = ObjectProperties::new(&object.properties);
+                let entity = match constructor(&properties, &mut commands) {
+                    Ok(entity) => entity,
+                    Err(error) => {
+                        warn!("{}: object {} ({}): {}", map_name, object.id(), object.user_type, error);
+                        continue;
+                    }
+                };
+
+                // Rectangles are placed by their center, everything else by its point.
+                let position = match object.shape {
+                    ObjectShape::Rect { width, height } => {
+                        tiled_to_world(map_height_px, object.x + width / 2.0, object.y + height / 2.0)
+                    }
+                    _ => tiled_to_world(map_height_px, object.x, object.y),
+                };
+                commands.entity(entity).insert((
+                    SpatialBundle::from_transform(Transform::from_translation(position.extend(0.5))),
+                    MapObject {
+                        tile: world_to_tile(position.extend(0.0)),
+                    },
+                    StateScoped(InNight),
+                ));
+                count += 1;
+            }
+        }
+
+        info!("Spawned {} objects from {}", count, map_name);
+        spawned.0 = Some(handle.0.id());
+    }
+}
+
+fn patrol_guards(
+    time: Res<Time>,
+    grid: Res<LevelGrid>,
+    mut guards: Query<(&mut Guard, &MapObject, &mut Transform)>,
+) {
+    for (mut guard, object, mut transform) in &mut guards {
+        let reach = guard.patrol.max(0) as f32;
+        let next = guard.offset + guard.heading * guard.speed * time.delta_seconds();
+        let ahead = object.tile + IVec2::new((next + guard.heading * 0.5).round() as i32, 0);
+        // Turn around at either end of the patrol or at anything in the way.
+        if next.abs() > reach || !grid.is_walkable(ahead) {
+            guard.heading = -guard.heading;
+            continue;
+        }
+        guard.offset = next;
+        transform.translation =
+            tile_to_world(object.tile, GUARD_Z) + Vec3::X * guard.offset * TILE_SIZE;
+    }
+}
+
+fn fire_triggers(
+    mut commands: Commands,
+    mut steps: EventReader<PlayerStepped>,
+    mut popup_queue: ResMut<PopupQueue>,
+    locale: Res<Locale>,
+    triggers: Query<(Entity, &Trigger, &MapObject)>,
+) {
+    for step in steps.read() {
+        let tile = world_to_tile(step.to);
+        for (entity, trigger, object) in &triggers {
+            if object.tile != tile {
+                continue;
+            }
+            // Map authors may write the text straight in instead of adding a key.
+            if locale.contains(&trigger.message) {
+                popup_queue.push(locale.get(&trigger.message));
+            } else {
+                popup_queue.push(trigger.message.clone());
+            }
+            if trigger.once {
+                commands.entity(entity).despawn_recursive();
+            }
+        }
+    }
+}
+
+// Runs every frame so a freshly rebuilt grid picks the doors up again.
+fn block_closed_doors(mut grid: ResMut<LevelGrid>, doors: Query<(&Door, &MapObject)>) {
+    if !grid.is_loaded() {
+        return;
+    }
+    for (door, object) in &doors {
+        let blocked = !door.open;
+        if grid.is_walkable(object.tile) == blocked {
+            grid.set_blocked(object.tile, blocked);
+        }
+    }
+}
+
+fn open_doors(
+    mut input: ResMut<GameInput>,
+    player: Res<PosVar>,
+    mut doors: Query<(&mut Door, &MapObject)>,
+    mut popup_queue: ResMut<PopupQueue>,
+    mut sfx: EventWriter<PlaySfx>,
+    locale: Res<Locale>,
+) {
+    if !input.just_pressed(GameAction::Interact) {
+        return;
+    }
+
+    let player_tile = world_to_tile(player.pos_vec);
+    let Some((mut door, _)) = doors.iter_mut().find(|(door, object)| {
+        !door.open && (object.tile - player_tile).abs().element_sum() <= DOOR_RANGE
+    }) else {
+        return;
+    };
+
+    input.clear_just_pressed(GameAction::Interact);
+    if door.locked {
+        popup_queue.push(locale.get("door-locked"));
+        return;
+    }
+    door.open = true;
+    sfx.send(PlaySfx::new(SoundEffect::DoorSlowOpen));
+}
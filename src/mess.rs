@@ -0,0 +1,380 @@
+use bevy::prelude::*;
+use std::collections::HashSet;
+
+use crate::audio::{PlaySfx, SoundEffect};
+use crate::input::{GameAction, GameInput, InteractSet};
+use crate::energy::Energy;
+use crate::level::{tile_to_world, world_to_tile, LevelGrid, RoomKind};
+use crate::map_objects::{MapObject, RegisterMapObject};
+use crate::rng::GameRng;
+use crate::text::{no_modal_open, GameTime};
+use crate::{GameState, InNight, PosVar};
+
+pub struct MessPlugin;
+
+// A new mess appears every few game minutes.
+const SPAWN_INTERVAL_MINUTES: u32 = 4;
+// Spills left alone this long start running into the next tile.
+const SPREAD_AFTER_MINUTES: u32 = 6;
+const CLEAN_AMOUNT: f32 = 0.34;
+const CLEAN_ENERGY_COST: f32 = 2.0;
+const MESS_Z: f32 = 0.9;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MessKind {
+    Dirt,
+    Spill,
+    Trash,
+}
+
+impl MessKind {
+    fn from_property(value: &str) -> Option<Self> {
+        match value {
+            "dirt" => Some(MessKind::Dirt),
+            "spill" => Some(MessKind::Spill),
+            "trash" => Some(MessKind::Trash),
+            _ => None,
+        }
+    }
+
+    fn color(&self) -> Color {
+        match self {
+            MessKind::Dirt => Color::srgba(0.4, 0.3, 0.2, 0.8),
+            MessKind::Spill => Color::srgba(0.3, 0.5, 0.9, 0.7),
+            MessKind::Trash => Color::srgb(0.6, 0.6, 0.55),
+        }
+    }
+
+    // How much the mess grows every game minute it is left alone.
+    fn growth_per_minute(&self) -> f32 {
+        match self {
+            MessKind::Dirt => 0.05,
+            MessKind::Spill => 0.1,
+            MessKind::Trash => 0.02,
+        }
+    }
+
+    fn clean_sound(&self) -> SoundEffect {
+        match self {
+            MessKind::Dirt => SoundEffect::Click,
+            MessKind::Spill => SoundEffect::WaterSplash,
+            MessKind::Trash => SoundEffect::Crunch,
+        }
+    }
+}
+
+impl RoomKind {
+    // How likely a room is to get the next mess, and what kind it will be.
+    fn mess_weight(&self) -> u32 {
+        match self {
+            RoomKind::BreakRoom => 5,
+            RoomKind::Office => 3,
+            RoomKind::Conference => 3,
+            RoomKind::Restroom => 4,
+            RoomKind::Lobby => 2,
+            RoomKind::Hallway => 1,
+        }
+    }
+
+    fn mess_kinds(&self) -> [(MessKind, u32); 3] {
+        match self {
+            RoomKind::BreakRoom => [(MessKind::Dirt, 1), (MessKind::Spill, 5), (MessKind::Trash, 4)],
+            RoomKind::Office => [(MessKind::Dirt, 2), (MessKind::Spill, 2), (MessKind::Trash, 5)],
+            RoomKind::Conference => [(MessKind::Dirt, 1), (MessKind::Spill, 3), (MessKind::Trash, 5)],
+            RoomKind::Restroom => [(MessKind::Dirt, 2), (MessKind::Spill, 6), (MessKind::Trash, 1)],
+            RoomKind::Lobby => [(MessKind::Dirt, 6), (MessKind::Spill, 1), (MessKind::Trash, 2)],
+            RoomKind::Hallway => [(MessKind::Dirt, 5), (MessKind::Spill, 1), (MessKind::Trash, 1)],
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct Mess {
+    pub kind: MessKind,
+    pub tile: IVec2,
+    // 0.0 is spotless, 1.0 is as bad as a single tile gets.
+    pub amount: f32,
+    pub untended_since: u32,
+}
+
+// A "Task" object in the map: a mess waiting there from the start of the night.
+#[derive(Component)]
+pub struct TaskSpot {
+    pub kind: Option<MessKind>,
+}
+
+// Ask for a mess to appear, either on a given tile or somewhere in a kind of room.
+#[derive(Event)]
+pub struct SpawnMess {
+    pub kind: Option<MessKind>,
+    pub tile: Option<IVec2>,
+    pub room: Option<RoomKind>,
+}
+
+#[derive(Resource)]
+pub struct MessSimulator {
+    next_spawn_minute: u32,
+    last_minute: u32,
+}
+
+impl Default for MessSimulator {
+    fn default() -> Self {
+        Self {
+            next_spawn_minute: SPAWN_INTERVAL_MINUTES,
+            last_minute: 0,
+        }
+    }
+}
+
+#[derive(Resource, Default, PartialEq)]
+pub struct MessLevel {
+    pub total: f32,
+    pub active: usize,
+    pub cleaned: u32,
+}
+
+impl MessLevel {
+    // 1.0 when the office is spotless, falling towards 0.0 as mess piles up.
+    pub fn cleanliness(&self) -> f32 {
+        1.0 / (1.0 + self.total / 5.0)
+    }
+}
+
+impl Plugin for MessPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<MessSimulator>();
+        app.init_resource::<MessLevel>();
+        app.add_event::<SpawnMess>();
+        app.register_map_object("Task", |properties| {
+            let kind = match properties.get::<String>("kind")? {
+                Some(kind) => Some(
+                    MessKind::from_property(&kind).ok_or_else(|| format!("unknown mess kind '{}'", kind))?,
+                ),
+                None => None,
+            };
+            Ok(TaskSpot { kind })
+        });
+        app.add_systems(OnEnter(InNight), reset_messes);
+        app.add_systems(
+            Update,
+            (
+                place_task_messes,
+                schedule_messes,
+                grow_and_spread_messes,
+                spawn_messes,
+                clean_mess.run_if(no_modal_open).in_set(InteractSet::Mess),
+                update_mess_sprites,
+                update_mess_level,
+            )
+                .chain()
+                .run_if(in_state(GameState::Playing)),
+        );
+    }
+}
+
+fn reset_messes(mut commands: Commands) {
+    commands.insert_resource(MessSimulator::default());
+    commands.insert_resource(MessLevel::default());
+}
+
+fn place_task_messes(
+    mut commands: Commands,
+    grid: Res<LevelGrid>,
+    spots: Query<(Entity, &TaskSpot, &MapObject)>,
+    mut spawn_events: EventWriter<SpawnMess>,
+) {
+    // spawn_messes drops requests until the grid is ready.
+    if !grid.is_loaded() {
+        return;
+    }
+    for (entity, spot, object) in &spots {
+        spawn_events.send(SpawnMess {
+            kind: spot.kind,
+            tile: Some(object.tile),
+            room: None,
+        });
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+fn schedule_messes(
+    game_time: Res<GameTime>,
+    mut simulator: ResMut<MessSimulator>,
+    mut spawn_events: EventWriter<SpawnMess>,
+) {
+    let now = game_time.elapsed_minutes();
+    if now < simulator.next_spawn_minute {
+        return;
+    }
+
+    simulator.next_spawn_minute = now + SPAWN_INTERVAL_MINUTES;
+    spawn_events.send(SpawnMess {
+        kind: None,
+        tile: None,
+        room: None,
+    });
+}
+
+fn grow_and_spread_messes(
+    game_time: Res<GameTime>,
+    grid: Res<LevelGrid>,
+    mut rng: ResMut<GameRng>,
+    mut simulator: ResMut<MessSimulator>,
+    mut messes: Query<&mut Mess>,
+    mut spawn_events: EventWriter<SpawnMess>,
+) {
+    let now = game_time.elapsed_minutes();
+    if now == simulator.last_minute {
+        return;
+    }
+    let minutes_passed = now.saturating_sub(simulator.last_minute) as f32;
+    simulator.last_minute = now;
+
+    let occupied: HashSet<IVec2> = messes.iter().map(|mess| mess.tile).collect();
+
+    for mut mess in &mut messes {
+        mess.amount = (mess.amount + mess.kind.growth_per_minute() * minutes_passed).min(1.0);
+
+        let untended = now.saturating_sub(mess.untended_since);
+        if mess.kind == MessKind::Spill && untended >= SPREAD_AFTER_MINUTES && mess.amount >= 1.0 {
+            let free: Vec<IVec2> = grid
+                .walkable_neighbours(mess.tile)
+                .filter(|tile| !occupied.contains(tile))
+                .collect();
+            if let Some(tile) = rng.pick(&free) {
+                spawn_events.send(SpawnMess {
+                    kind: Some(MessKind::Spill),
+                    tile: Some(*tile),
+                    room: None,
+                });
+            }
+            // Give the next spread a moment so a spill does not flood the room at once.
+            mess.untended_since = now;
+        }
+    }
+}
+
+fn spawn_messes(
+    mut commands: Commands,
+    mut spawn_events: EventReader<SpawnMess>,
+    grid: Res<LevelGrid>,
+    game_time: Res<GameTime>,
+    mut rng: ResMut<GameRng>,
+    existing: Query<&Mess>,
+) {
+    if !grid.is_loaded() {
+        spawn_events.clear();
+        return;
+    }
+
+    let mut occupied: HashSet<IVec2> = existing.iter().map(|mess| mess.tile).collect();
+
+    for request in spawn_events.read() {
+        let tile = match request.tile {
+            Some(tile) => Some(tile),
+            None => {
+                let rooms: Vec<_> = grid
+                    .rooms
+                    .iter()
+                    .filter(|room| request.room.is_none_or(|kind| room.kind == kind))
+                    .map(|room| (room, room.kind.mess_weight()))
+                    .collect();
+                rng.pick_weighted(&rooms).and_then(|room| {
+                    let free: Vec<IVec2> = grid
+                        .walkable_tiles_in(room)
+                        .into_iter()
+                        .filter(|tile| !occupied.contains(tile))
+                        .collect();
+                    rng.pick(&free).copied()
+                })
+            }
+        };
+
+        let Some(tile) = tile else {
+            continue;
+        };
+        if occupied.contains(&tile) || !grid.is_walkable(tile) {
+            continue;
+        }
+
+        let kind = match request.kind {
+            Some(kind) => kind,
+            None => *rng
+                .pick_weighted(&grid.room_kind_at(tile).mess_kinds())
+                .unwrap_or(&MessKind::Dirt),
+        };
+
+        occupied.insert(tile);
+        commands.spawn((
+            SpriteBundle {
+                sprite: Sprite {
+                    color: kind.color(),
+                    custom_size: Some(Vec2::splat(16.)),
+                    ..default()
+                },
+                transform: Transform::from_translation(tile_to_world(tile, MESS_Z)),
+                ..default()
+            },
+            Mess {
+                kind,
+                tile,
+                amount: 0.3,
+                untended_since: game_time.elapsed_minutes(),
+            },
+            StateScoped(InNight),
+        ));
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn clean_mess(
+    mut commands: Commands,
+    mut input: ResMut<GameInput>,
+    player: Res<PosVar>,
+    game_time: Res<GameTime>,
+    mut messes: Query<(Entity, &mut Mess)>,
+    mut energy: ResMut<Energy>,
+    mut mess_level: ResMut<MessLevel>,
+    mut sfx: EventWriter<PlaySfx>,
+) {
+    if !input.just_pressed(GameAction::Interact) {
+        return;
+    }
+
+    // Cliff can reach the tile he stands on and the ones next to it.
+    let player_tile = world_to_tile(player.pos_vec);
+    let Some((entity, mut mess)) = messes
+        .iter_mut()
+        .filter(|(_, mess)| (mess.tile - player_tile).abs().element_sum() <= 1)
+        .min_by_key(|(_, mess)| (mess.tile - player_tile).abs().element_sum())
+    else {
+        return;
+    };
+
+    input.clear_just_pressed(GameAction::Interact);
+    mess.amount -= CLEAN_AMOUNT;
+    mess.untended_since = game_time.elapsed_minutes();
+    energy.spend(CLEAN_ENERGY_COST);
+    sfx.send(PlaySfx::new(mess.kind.clean_sound()).with_pitch_variation(0.1));
+
+    if mess.amount <= 0.0 {
+        commands.entity(entity).despawn_recursive();
+        mess_level.cleaned += 1;
+    }
+}
+
+fn update_mess_sprites(mut messes: Query<(&Mess, &mut Transform), Changed<Mess>>) {
+    for (mess, mut transform) in &mut messes {
+        transform.scale = Vec3::splat(0.5 + 0.5 * mess.amount.clamp(0.0, 1.0));
+    }
+}
+
+fn update_mess_level(messes: Query<&Mess>, mut mess_level: ResMut<MessLevel>) {
+    // Only mark the level changed when it did, for the systems that react to it.
+    let level = MessLevel {
+        total: messes.iter().map(|mess| mess.amount.max(0.0)).sum(),
+        active: messes.iter().count(),
+        cleaned: mess_level.cleaned,
+    };
+    mess_level.set_if_neq(level);
+}
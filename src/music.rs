@@ -0,0 +1,168 @@
+use bevy::prelude::*;
+use bevy_kira_audio::prelude::*;
+use std::time::Duration;
+
+use crate::audio::Background;
+use crate::mess::MessLevel;
+use crate::night_events::{Disturbance, NightEvent};
+use crate::transition::Transition;
+use crate::GameState;
+
+pub struct MusicPlugin;
+
+const DEFAULT_CROSSFADE: Duration = Duration::from_millis(1500);
+// Mess level at which the night starts to feel out of hand.
+const TENSE_MESS_LEVEL: f32 = 8.0;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MusicTrack {
+    FiftiesBit,
+    Celebrate,
+    DeitiesGetTakeoutToo,
+    DownTheRiverWeGo,
+    ForestOfTheKing,
+}
+
+impl MusicTrack {
+    pub fn path(&self) -> &'static str {
+        match self {
+            MusicTrack::FiftiesBit => "retroindiejosh_50s-bit.ogg",
+            MusicTrack::Celebrate => "retroindiejosh_celebrate.ogg",
+            MusicTrack::DeitiesGetTakeoutToo => "retroindiejosh_deities-get-takeout-too.ogg",
+            MusicTrack::DownTheRiverWeGo => "retroindiejosh_down-the-river-we-go.ogg",
+            MusicTrack::ForestOfTheKing => "retroindiejosh_forest-of-the-king.ogg",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MusicMood {
+    Menu,
+    Gameplay,
+    Tension,
+    Victory,
+    Defeat,
+}
+
+impl MusicMood {
+    pub fn track(&self) -> MusicTrack {
+        match self {
+            MusicMood::Menu => MusicTrack::ForestOfTheKing,
+            MusicMood::Gameplay => MusicTrack::FiftiesBit,
+            MusicMood::Tension => MusicTrack::DeitiesGetTakeoutToo,
+            MusicMood::Victory => MusicTrack::Celebrate,
+            MusicMood::Defeat => MusicTrack::DownTheRiverWeGo,
+        }
+    }
+}
+
+// Forces a mood regardless of game state, e.g. for the results screen. `None` hands
+// control back to the director.
+#[derive(Event)]
+pub struct SetMusicMood(pub Option<MusicMood>);
+
+#[derive(Resource)]
+pub struct MusicDirector {
+    pub mood: Option<MusicMood>,
+    pub crossfade: Duration,
+    forced: Option<MusicMood>,
+    playing: Option<(MusicTrack, Handle<AudioInstance>)>,
+}
+
+impl Default for MusicDirector {
+    fn default() -> Self {
+        Self {
+            mood: None,
+            crossfade: DEFAULT_CROSSFADE,
+            forced: None,
+            playing: None,
+        }
+    }
+}
+
+impl Plugin for MusicPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<MusicDirector>();
+        app.add_event::<SetMusicMood>();
+        app.add_systems(Update, (choose_mood, play_mood_track).chain());
+    }
+}
+
+fn choose_mood(
+    state: Res<State<GameState>>,
+    mut director: ResMut<MusicDirector>,
+    mut requests: EventReader<SetMusicMood>,
+    mess_level: Option<Res<MessLevel>>,
+    disturbances: Query<&Disturbance>,
+    transition: Option<Res<Transition>>,
+) {
+    for SetMusicMood(mood) in requests.read() {
+        director.forced = *mood;
+    }
+
+    // Start on the next screen's music as soon as the transition to it begins.
+    let state = transition
+        .as_ref()
+        .and_then(|transition| transition.destination())
+        .unwrap_or(state.get());
+    let mood = if let Some(forced) = director.forced {
+        Some(forced)
+    } else {
+        match state {
+            // The results screen forces its own mood once it is entered.
+            GameState::Menu | GameState::Settings | GameState::Results => Some(MusicMood::Menu),
+            GameState::Playing | GameState::Paused | GameState::Loading => {
+                let alarm = disturbances
+                    .iter()
+                    .any(|disturbance| disturbance.event == NightEvent::Alarm);
+                let messy = mess_level.is_some_and(|level| level.total >= TENSE_MESS_LEVEL);
+                if alarm || messy {
+                    Some(MusicMood::Tension)
+                } else {
+                    Some(MusicMood::Gameplay)
+                }
+            }
+            GameState::Exit => None,
+        }
+    };
+
+    if director.mood != mood {
+        director.mood = mood;
+    }
+}
+
+fn play_mood_track(
+    mut director: ResMut<MusicDirector>,
+    background: Res<AudioChannel<Background>>,
+    mut audio_instances: ResMut<Assets<AudioInstance>>,
+    asset_server: Res<AssetServer>,
+    transition: Option<Res<Transition>>,
+) {
+    let wanted = director.mood.map(|mood| mood.track());
+    let current = director.playing.as_ref().map(|(track, _)| *track);
+    if wanted == current {
+        return;
+    }
+
+    // Keep the crossfade in step with a screen transition when one is running.
+    let crossfade = match transition {
+        Some(transition) if transition.is_running() => transition.total_duration(),
+        _ => director.crossfade,
+    };
+    let fade = AudioTween::linear(crossfade);
+    if let Some((_, handle)) = director.playing.take() {
+        if let Some(instance) = audio_instances.get_mut(&handle) {
+            instance.stop(fade.clone());
+        }
+    }
+
+    if let Some(track) = wanted {
+        info!("Music: {:?}", track);
+        let handle = background
+            .play(asset_server.load(track.path()))
+            .looped()
+            .fade_in(fade)
+            .handle();
+        director.playing = Some((track, handle));
+    }
+}
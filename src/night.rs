@@ -0,0 +1,247 @@
+use bevy::prelude::*;
+
+use crate::energy::Energy;
+use crate::ledger::Ledger;
+use crate::mess::MessLevel;
+use crate::music::{MusicMood, SetMusicMood};
+use crate::night_events::{NightEvent, RandomEvent, ScriptedEvent};
+use crate::text::GameTime;
+use crate::transition::{transition_idle, TransitionTo};
+use crate::GameState;
+
+pub struct NightPlugin;
+
+// Every level is a night in the same office, with more to do in less time.
+pub struct LevelConfig {
+    pub map: &'static str,
+    pub tasks_required: u32,
+    pub shift_minutes: u32,
+    pub scripted_events: &'static [ScriptedEvent],
+    pub random_events: &'static [RandomEvent],
+}
+
+pub const LEVELS: [LevelConfig; 3] = [
+    LevelConfig {
+        map: "tilemap_level1.tmx",
+        tasks_required: 8,
+        shift_minutes: 180,
+        scripted_events: &[
+            ScriptedEvent { event: NightEvent::PhoneRing, at_minute: 15 },
+            ScriptedEvent { event: NightEvent::Thunder, at_minute: 45 },
+        ],
+        random_events: RANDOM_EVENTS,
+    },
+    LevelConfig {
+        map: "tilemap_level1.tmx",
+        tasks_required: 12,
+        shift_minutes: 180,
+        scripted_events: &[
+            ScriptedEvent { event: NightEvent::PhoneRing, at_minute: 15 },
+            ScriptedEvent { event: NightEvent::Thunder, at_minute: 45 },
+            ScriptedEvent { event: NightEvent::Alarm, at_minute: 90 },
+        ],
+        random_events: RANDOM_EVENTS,
+    },
+    LevelConfig {
+        map: "tilemap_level1.tmx",
+        tasks_required: 16,
+        shift_minutes: 150,
+        scripted_events: &[
+            ScriptedEvent { event: NightEvent::PhoneRing, at_minute: 10 },
+            ScriptedEvent { event: NightEvent::Thunder, at_minute: 30 },
+            ScriptedEvent { event: NightEvent::Alarm, at_minute: 60 },
+            ScriptedEvent { event: NightEvent::PhoneRing, at_minute: 100 },
+        ],
+        random_events: RANDOM_EVENTS,
+    },
+];
+
+// What can happen on any night, rolled once a game minute.
+const RANDOM_EVENTS: &[RandomEvent] = &[
+    RandomEvent { event: NightEvent::PhoneRing, chance_per_minute: 0.03, earliest_minute: 30, cooldown_minutes: 30 },
+    RandomEvent { event: NightEvent::Alarm, chance_per_minute: 0.02, earliest_minute: 60, cooldown_minutes: 60 },
+    RandomEvent { event: NightEvent::Thunder, chance_per_minute: 0.02, earliest_minute: 50, cooldown_minutes: 40 },
+    RandomEvent { event: NightEvent::DoorCreak, chance_per_minute: 0.03, earliest_minute: 20, cooldown_minutes: 25 },
+    RandomEvent { event: NightEvent::CatMeow, chance_per_minute: 0.04, earliest_minute: 10, cooldown_minutes: 20 },
+];
+
+// Index into LEVELS.
+#[derive(Resource, Default)]
+pub struct CurrentLevel(pub usize);
+
+impl CurrentLevel {
+    pub fn config(&self) -> &'static LevelConfig {
+        &LEVELS[self.0.min(LEVELS.len() - 1)]
+    }
+
+    pub fn number(&self) -> usize {
+        self.0 + 1
+    }
+
+    pub fn has_next(&self) -> bool {
+        self.0 + 1 < LEVELS.len()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NightOutcome {
+    // Every task done before the shift ended.
+    Finished,
+    ShiftOver,
+    Exhausted,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Grade {
+    A,
+    B,
+    C,
+    D,
+    F,
+}
+
+impl Grade {
+    fn from_score(score: f32) -> Self {
+        match score {
+            s if s >= 0.85 => Grade::A,
+            s if s >= 0.7 => Grade::B,
+            s if s >= 0.55 => Grade::C,
+            s if s >= 0.4 => Grade::D,
+            _ => Grade::F,
+        }
+    }
+
+    pub fn letter(&self) -> &'static str {
+        match self {
+            Grade::A => "A",
+            Grade::B => "B",
+            Grade::C => "C",
+            Grade::D => "D",
+            Grade::F => "F",
+        }
+    }
+
+    // Good enough to celebrate and to unlock the next level.
+    pub fn is_pass(&self) -> bool {
+        *self <= Grade::B
+    }
+}
+
+// Filled in when a night ends, for the results screen.
+#[derive(Resource)]
+pub struct NightReport {
+    pub outcome: NightOutcome,
+    pub tasks_completed: u32,
+    pub tasks_total: u32,
+    pub minutes_used: u32,
+    pub earnings: u32,
+    pub damages: u32,
+    pub grade: Grade,
+}
+
+impl NightReport {
+    fn new(
+        outcome: NightOutcome,
+        level: &LevelConfig,
+        mess_level: &MessLevel,
+        ledger: &Ledger,
+        minutes_used: u32,
+    ) -> Self {
+        let completion = (mess_level.cleaned as f32 / level.tasks_required as f32).min(1.0);
+        let time_bonus = match outcome {
+            NightOutcome::Finished => 1.0 - minutes_used as f32 / level.shift_minutes as f32,
+            _ => 0.0,
+        };
+        let money = (ledger.balance() as f32 / 50.0).clamp(-0.2, 0.1);
+        let score = 0.6 * completion + 0.2 * mess_level.cleanliness() + 0.1 * time_bonus + money;
+
+        let mut grade = Grade::from_score(score);
+        // Passing out on the job caps the grade.
+        if outcome == NightOutcome::Exhausted {
+            grade = grade.max(Grade::D);
+        }
+
+        Self {
+            outcome,
+            tasks_completed: mess_level.cleaned,
+            tasks_total: level.tasks_required,
+            minutes_used,
+            earnings: ledger.earnings,
+            damages: ledger.damages,
+            grade,
+        }
+    }
+}
+
+// Moves on to the next level and starts its night.
+#[derive(Event, Clone)]
+pub struct AdvanceLevel;
+
+impl Plugin for NightPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<CurrentLevel>();
+        app.add_event::<AdvanceLevel>();
+        // Idle transitions only, so the night ends once rather than every frame of the fade.
+        app.add_systems(
+            Update,
+            end_night.run_if(in_state(GameState::Playing).and_then(transition_idle)),
+        );
+        app.add_systems(Update, advance_level);
+        app.add_systems(OnEnter(GameState::Results), play_results_music);
+        app.add_systems(OnExit(GameState::Results), release_results_music);
+    }
+}
+
+fn end_night(
+    mut commands: Commands,
+    level: Res<CurrentLevel>,
+    game_time: Res<GameTime>,
+    energy: Res<Energy>,
+    mess_level: Res<MessLevel>,
+    ledger: Res<Ledger>,
+    mut transitions: EventWriter<TransitionTo>,
+) {
+    let config = level.config();
+    let minutes = game_time.elapsed_minutes();
+    let outcome = if mess_level.cleaned >= config.tasks_required {
+        NightOutcome::Finished
+    } else if energy.current <= 0.0 {
+        NightOutcome::Exhausted
+    } else if minutes >= config.shift_minutes {
+        NightOutcome::ShiftOver
+    } else {
+        return;
+    };
+
+    let report = NightReport::new(outcome, config, &mess_level, &ledger, minutes.min(config.shift_minutes));
+    info!("Night over: {:?}, grade {}", report.outcome, report.grade.letter());
+    commands.insert_resource(report);
+    transitions.send(TransitionTo::new(GameState::Results));
+}
+
+fn advance_level(
+    mut requests: EventReader<AdvanceLevel>,
+    mut level: ResMut<CurrentLevel>,
+    mut transitions: EventWriter<TransitionTo>,
+) {
+    for _ in requests.read() {
+        if !level.has_next() {
+            continue;
+        }
+        level.0 += 1;
+        transitions.send(TransitionTo::new(GameState::Loading));
+    }
+}
+
+fn play_results_music(report: Res<NightReport>, mut music: EventWriter<SetMusicMood>) {
+    let mood = if report.grade.is_pass() {
+        MusicMood::Victory
+    } else {
+        MusicMood::Defeat
+    };
+    music.send(SetMusicMood(Some(mood)));
+}
+
+fn release_results_music(mut music: EventWriter<SetMusicMood>) {
+    music.send(SetMusicMood(None));
+}
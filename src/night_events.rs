@@ -0,0 +1,319 @@
+use bevy::prelude::*;
+use bevy_text_popup::TextPopupEvent;
+
+use crate::audio::{PlaySfx, SoundEffect};
+use crate::input::{GameAction, GameInput, InteractSet};
+use crate::energy::Energy;
+use crate::ledger::Ledger;
+use crate::level::{tile_to_world, world_to_tile, LevelGrid, RoomKind};
+use crate::locale::Locale;
+use crate::mess::{MessKind, SpawnMess};
+use crate::night::CurrentLevel;
+use crate::rng::GameRng;
+use crate::text::{no_modal_open, notice_popup, GameTime, PopupQueue};
+use crate::{GameState, InNight, PosVar};
+
+pub struct NightEventsPlugin;
+
+const RESOLVE_RANGE: i32 = 1;
+// How long the phone rings before the caller gives up.
+const PHONE_DEADLINE_MINUTES: u32 = 10;
+const PHONE_ANSWERED_BONUS: u32 = 15;
+const PHONE_MISSED_PENALTY: u32 = 10;
+const ALARM_ENERGY_PER_MINUTE: f32 = 1.5;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NightEvent {
+    PhoneRing,
+    Alarm,
+    Thunder,
+    DoorCreak,
+    CatMeow,
+}
+
+impl NightEvent {
+    fn sound(&self) -> SoundEffect {
+        match self {
+            NightEvent::PhoneRing => SoundEffect::PhoneRing,
+            NightEvent::Alarm => SoundEffect::DigitalAlarm,
+            NightEvent::Thunder => SoundEffect::Thunder,
+            NightEvent::DoorCreak => SoundEffect::DoorSlowOpen,
+            NightEvent::CatMeow => SoundEffect::CatMeow,
+        }
+    }
+
+    fn message_key(&self) -> &'static str {
+        match self {
+            NightEvent::PhoneRing => "event-phone-ring",
+            NightEvent::Alarm => "event-alarm",
+            NightEvent::Thunder => "event-thunder",
+            NightEvent::DoorCreak => "event-door-creak",
+            NightEvent::CatMeow => "event-cat-meow",
+        }
+    }
+
+    // Background noise that needs nothing from Cliff, so it should not stop him either.
+    fn is_ambient(&self) -> bool {
+        matches!(self, NightEvent::Thunder | NightEvent::DoorCreak | NightEvent::CatMeow)
+    }
+}
+
+// Fires once when the clock reaches `at_minute`.
+pub struct ScriptedEvent {
+    pub event: NightEvent,
+    pub at_minute: u32,
+}
+
+// Rolls once every game minute after `earliest_minute`.
+pub struct RandomEvent {
+    pub event: NightEvent,
+    pub chance_per_minute: f32,
+    pub earliest_minute: u32,
+    pub cooldown_minutes: u32,
+}
+
+// The night's events, as set out in its level config. Empty until a night starts.
+#[derive(Resource, Default)]
+pub struct EventSchedule {
+    pub scripted: &'static [ScriptedEvent],
+    pub random: &'static [RandomEvent],
+    last_fired: Vec<Option<u32>>,
+    last_minute: u32,
+}
+
+impl EventSchedule {
+    pub fn new(scripted: &'static [ScriptedEvent], random: &'static [RandomEvent]) -> Self {
+        Self {
+            scripted,
+            random,
+            last_fired: vec![None; random.len()],
+            last_minute: 0,
+        }
+    }
+}
+
+#[derive(Event)]
+pub struct NightEventFired(pub NightEvent);
+
+// Something that needs Cliff to walk over and deal with it.
+#[derive(Component)]
+pub struct Disturbance {
+    pub event: NightEvent,
+    pub tile: IVec2,
+    pub deadline: Option<u32>,
+}
+
+impl Plugin for NightEventsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<EventSchedule>();
+        app.add_event::<NightEventFired>();
+        app.add_systems(OnEnter(InNight), reset_event_schedule);
+        app.add_systems(
+            Update,
+            (
+                run_event_schedule,
+                apply_night_events,
+                resolve_disturbance.run_if(no_modal_open).in_set(InteractSet::Disturbance),
+                tick_disturbances,
+            )
+                .chain()
+                .run_if(in_state(GameState::Playing)),
+        );
+    }
+}
+
+fn reset_event_schedule(mut commands: Commands, level: Res<CurrentLevel>) {
+    let config = level.config();
+    commands.insert_resource(EventSchedule::new(config.scripted_events, config.random_events));
+}
+
+fn run_event_schedule(
+    game_time: Res<GameTime>,
+    mut schedule: ResMut<EventSchedule>,
+    mut rng: ResMut<GameRng>,
+    mut fired: EventWriter<NightEventFired>,
+) {
+    let now = game_time.elapsed_minutes();
+    if now == schedule.last_minute {
+        return;
+    }
+    let since = schedule.last_minute;
+    schedule.last_minute = now;
+
+    for scripted in schedule.scripted {
+        if scripted.at_minute > since && scripted.at_minute <= now {
+            fired.send(NightEventFired(scripted.event));
+        }
+    }
+
+    let schedule = &mut *schedule;
+    for (random, last_fired) in schedule.random.iter().zip(schedule.last_fired.iter_mut()) {
+        if now < random.earliest_minute {
+            continue;
+        }
+        if last_fired.is_some_and(|minute| now < minute + random.cooldown_minutes) {
+            continue;
+        }
+        if rng.chance(random.chance_per_minute) {
+            *last_fired = Some(now);
+            fired.send(NightEventFired(random.event));
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn apply_night_events(
+    mut commands: Commands,
+    mut fired: EventReader<NightEventFired>,
+    mut popup_queue: ResMut<PopupQueue>,
+    mut text_popup_events: EventWriter<TextPopupEvent>,
+    mut spawn_mess: EventWriter<SpawnMess>,
+    grid: Res<LevelGrid>,
+    game_time: Res<GameTime>,
+    mut rng: ResMut<GameRng>,
+    disturbances: Query<&Disturbance>,
+    mut sfx: EventWriter<PlaySfx>,
+    locale: Res<Locale>,
+) {
+    for NightEventFired(event) in fired.read() {
+        info!("Night event: {:?}", event);
+        let message = locale.get(event.message_key());
+        if event.is_ambient() {
+            notice_popup(&mut text_popup_events, &message);
+        } else {
+            popup_queue.push(message);
+        }
+
+        match event {
+            NightEvent::PhoneRing | NightEvent::Alarm => {
+                // Only one of each ringing at a time.
+                if disturbances.iter().any(|disturbance| disturbance.event == *event) {
+                    continue;
+                }
+                let room = if *event == NightEvent::PhoneRing {
+                    RoomKind::Office
+                } else {
+                    RoomKind::Lobby
+                };
+                let tiles: Vec<IVec2> = grid
+                    .rooms
+                    .iter()
+                    .filter(|candidate| candidate.kind == room)
+                    .flat_map(|candidate| grid.walkable_tiles_in(candidate))
+                    .collect();
+                let Some(tile) = rng.pick(&tiles).copied() else {
+                    continue;
+                };
+
+                let deadline = (*event == NightEvent::PhoneRing)
+                    .then(|| game_time.elapsed_minutes() + PHONE_DEADLINE_MINUTES);
+                let disturbance = commands.spawn((
+                    SpriteBundle {
+                        sprite: Sprite {
+                            color: Color::srgb(0.9, 0.2, 0.2),
+                            custom_size: Some(Vec2::splat(8.)),
+                            ..default()
+                        },
+                        transform: Transform::from_translation(tile_to_world(tile, 1.1)),
+                        ..default()
+                    },
+                    Disturbance {
+                        event: *event,
+                        tile,
+                        deadline,
+                    },
+                    StateScoped(InNight),
+                )).id();
+                // The ringing stops on its own once the disturbance is despawned.
+                sfx.send(PlaySfx::new(event.sound()).looped_on(disturbance));
+            }
+            NightEvent::Thunder => {
+                sfx.send(PlaySfx::new(event.sound()));
+                for _ in 0..2 {
+                    spawn_mess.send(SpawnMess {
+                        kind: Some(MessKind::Spill),
+                        tile: None,
+                        room: Some(RoomKind::Office),
+                    });
+                }
+            }
+            NightEvent::DoorCreak => {
+                sfx.send(PlaySfx::new(event.sound()));
+                spawn_mess.send(SpawnMess {
+                    kind: Some(MessKind::Dirt),
+                    tile: None,
+                    room: Some(RoomKind::Lobby),
+                });
+            }
+            NightEvent::CatMeow => {
+                sfx.send(PlaySfx::new(event.sound()).with_pitch_variation(0.15));
+                spawn_mess.send(SpawnMess {
+                    kind: Some(MessKind::Trash),
+                    tile: None,
+                    room: Some(RoomKind::BreakRoom),
+                });
+            }
+        }
+    }
+}
+
+fn resolve_disturbance(
+    mut commands: Commands,
+    mut input: ResMut<GameInput>,
+    player: Res<PosVar>,
+    disturbances: Query<(Entity, &Disturbance)>,
+    mut ledger: ResMut<Ledger>,
+    mut popup_queue: ResMut<PopupQueue>,
+    locale: Res<Locale>,
+) {
+    if !input.just_pressed(GameAction::Interact) {
+        return;
+    }
+
+    let player_tile = world_to_tile(player.pos_vec);
+    for (entity, disturbance) in &disturbances {
+        if (disturbance.tile - player_tile).abs().element_sum() > RESOLVE_RANGE {
+            continue;
+        }
+
+        input.clear_just_pressed(GameAction::Interact);
+        match disturbance.event {
+            NightEvent::PhoneRing => {
+                ledger.earn(PHONE_ANSWERED_BONUS);
+                popup_queue.push(locale.get("event-phone-answered"));
+            }
+            NightEvent::Alarm => popup_queue.push(locale.get("event-alarm-off")),
+            _ => {}
+        }
+        commands.entity(entity).despawn_recursive();
+        break;
+    }
+}
+
+fn tick_disturbances(
+    mut commands: Commands,
+    game_time: Res<GameTime>,
+    disturbances: Query<(Entity, &Disturbance)>,
+    mut energy: ResMut<Energy>,
+    mut ledger: ResMut<Ledger>,
+    mut popup_queue: ResMut<PopupQueue>,
+    locale: Res<Locale>,
+) {
+    if !game_time.is_changed() {
+        return;
+    }
+
+    let now = game_time.elapsed_minutes();
+    for (entity, disturbance) in &disturbances {
+        match disturbance.event {
+            NightEvent::PhoneRing if disturbance.deadline.is_some_and(|deadline| now >= deadline) => {
+                ledger.charge(PHONE_MISSED_PENALTY);
+                popup_queue.push(locale.get("event-phone-missed"));
+                commands.entity(entity).despawn_recursive();
+            }
+            // The noise wears Cliff down until he switches it off.
+            NightEvent::Alarm => energy.spend(ALARM_ENERGY_PER_MINUTE),
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,298 @@
+use bevy::prelude::*;
+use bevy::time::{TimeSystem, TimeUpdateStrategy};
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::input::{GameAction, GameInput, GameInputSet, MenuPointer, PointerInputSet, TickInput};
+use crate::rng::GameRng;
+use crate::settings::UserSettings;
+
+// `--record <file>` logs every tick's input; `--replay <file>` plays one back.
+pub struct ReplayPlugin;
+
+// Real time given to each replayed frame past what reaches the next tick, so rounding
+// never leaves a frame without one.
+const TICK_SLACK: Duration = Duration::from_micros(1);
+
+enum ReplayMode {
+    Live,
+    Record(PathBuf),
+    Replay(PathBuf),
+}
+
+impl ReplayMode {
+    fn from_args(mut args: impl Iterator<Item = String>) -> Self {
+        let mut mode = ReplayMode::Live;
+        while let Some(arg) = args.next() {
+            match (arg.as_str(), args.next()) {
+                ("--record", Some(path)) => mode = ReplayMode::Record(path.into()),
+                ("--replay", Some(path)) => mode = ReplayMode::Replay(path.into()),
+                ("--record" | "--replay", None) => warn!("{} needs a file name", arg),
+                _ => warn!("Ignoring unknown argument '{}'", arg),
+            }
+        }
+        mode
+    }
+}
+
+// Everything a night depends on besides input: the seed, and the settings that
+// change how long dialogue takes or how fast the simulation runs.
+struct Recording {
+    seed: u64,
+    language: String,
+    text_speed: f32,
+    game_speed: f32,
+    ticks: Vec<TickInput>,
+}
+
+impl Recording {
+    fn parse(text: &str) -> Result<Self, String> {
+        let mut seed = None;
+        let mut language = None;
+        let mut text_speed = None;
+        let mut game_speed = None;
+        let mut ticks = Vec::new();
+
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            if let Some((key, value)) = line.split_once('=') {
+                let value = value.trim();
+                match key.trim() {
+                    "seed" => seed = value.parse().ok(),
+                    "language" => language = Some(value.to_string()),
+                    "text_speed" => text_speed = value.parse().ok(),
+                    "game_speed" => game_speed = value.parse().ok(),
+                    key => warn!("Unknown recording header '{}'", key),
+                }
+                continue;
+            }
+
+            let parts: Vec<&str> = line.split_whitespace().collect();
+            let (actions, pointer) = match parts[..] {
+                [_, actions] => (actions, None),
+                [_, actions, pointer] => (actions, Some(pointer)),
+                _ => return Err(format!("line {} is not 'tick actions [pointer]'", number + 1)),
+            };
+            let mut tick = TickInput::default();
+            if actions != "-" {
+                for name in actions.split(',') {
+                    let action = GameAction::from_name(name)
+                        .ok_or_else(|| format!("line {} has an unknown action '{}'", number + 1, name))?;
+                    tick.held.insert(action);
+                }
+            }
+            if let Some(pointer) = pointer {
+                let menu_pointer = parse_pointer(pointer)
+                    .ok_or_else(|| format!("line {} has a bad pointer '{}'", number + 1, pointer))?;
+                tick.menu_pointer = Some(menu_pointer);
+            }
+            ticks.push(tick);
+        }
+
+        Ok(Self {
+            seed: seed.ok_or("missing seed")?,
+            language: language.ok_or("missing language")?,
+            text_speed: text_speed.ok_or("missing text_speed")?,
+            game_speed: game_speed.ok_or("missing game_speed")?,
+            ticks,
+        })
+    }
+}
+
+fn pointer_text(pointer: MenuPointer) -> String {
+    match pointer {
+        MenuPointer::Hover(index) => format!("hover:{}", index),
+        MenuPointer::Click(index) => format!("click:{}", index),
+    }
+}
+
+fn parse_pointer(text: &str) -> Option<MenuPointer> {
+    let (kind, index) = text.split_once(':')?;
+    let index = index.parse().ok()?;
+    match kind {
+        "hover" => Some(MenuPointer::Hover(index)),
+        "click" => Some(MenuPointer::Click(index)),
+        _ => None,
+    }
+}
+
+#[derive(Resource)]
+struct Recorder {
+    // Written a line at a time, so a crash still leaves everything up to it.
+    file: File,
+    tick: u64,
+    // Input since the last tick; frames can pass between ticks, or several ticks
+    // can run in one frame.
+    pending: TickInput,
+}
+
+#[derive(Resource)]
+struct Playback {
+    ticks: Vec<TickInput>,
+    next: usize,
+}
+
+impl Playback {
+    fn current(&self) -> Option<&TickInput> {
+        self.ticks.get(self.next)
+    }
+}
+
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        match ReplayMode::from_args(std::env::args().skip(1)) {
+            ReplayMode::Live => {}
+            ReplayMode::Record(path) => start_recording(app, path),
+            ReplayMode::Replay(path) => start_replay(app, path),
+        }
+    }
+}
+
+// Needs UserSettings, so this goes after the settings plugin and before anything
+// that builds on the seed or the settings.
+fn start_recording(app: &mut App, path: PathBuf) {
+    let mut file = match File::create(&path) {
+        Ok(file) => file,
+        Err(error) => {
+            warn!("Could not create recording {}: {}", path.display(), error);
+            return;
+        }
+    };
+
+    let seed = GameRng::random_seed();
+    let settings = app.world().get_resource::<UserSettings>().cloned().unwrap_or_default();
+    let header = format!(
+        "# One line per fixed tick: tick, held actions, then any menu hover or click.\nseed = {}\nlanguage = {}\ntext_speed = {}\ngame_speed = {}\n",
+        seed, settings.language, settings.text_speed, settings.game_speed
+    );
+    if let Err(error) = file.write_all(header.as_bytes()) {
+        warn!("Could not write recording {}: {}", path.display(), error);
+        return;
+    }
+
+    info!("Recording input to {}", path.display());
+    app.insert_resource(GameRng::seeded(seed));
+    app.insert_resource(Recorder {
+        file,
+        tick: 0,
+        pending: TickInput::default(),
+    });
+    app.add_systems(
+        PreUpdate,
+        gather_input
+            .after(GameInputSet)
+            .run_if(resource_exists::<Recorder>),
+    );
+    app.add_systems(FixedFirst, record_tick.run_if(resource_exists::<Recorder>));
+}
+
+fn start_replay(app: &mut App, path: PathBuf) {
+    let recording = fs::read_to_string(&path)
+        .map_err(|error| error.to_string())
+        .and_then(|text| Recording::parse(&text));
+    let recording = match recording {
+        Ok(recording) => recording,
+        Err(error) => {
+            warn!("Could not load recording {}: {}", path.display(), error);
+            return;
+        }
+    };
+
+    info!("Replaying {} ticks from {}", recording.ticks.len(), path.display());
+    // Only for this run; save_settings skips a freshly inserted resource.
+    let mut settings = app.world().get_resource::<UserSettings>().cloned().unwrap_or_default();
+    settings.language = recording.language;
+    settings.text_speed = recording.text_speed;
+    settings.game_speed = recording.game_speed;
+    app.insert_resource(settings);
+    app.insert_resource(GameRng::seeded(recording.seed));
+    app.insert_resource(Playback {
+        ticks: recording.ticks,
+        next: 0,
+    });
+    app.add_systems(
+        First,
+        replay_frame_time
+            .before(TimeSystem)
+            .run_if(resource_exists::<Playback>),
+    );
+    app.add_systems(
+        PreUpdate,
+        replay_input
+            .in_set(GameInputSet)
+            .after(PointerInputSet)
+            .run_if(resource_exists::<Playback>),
+    );
+    app.add_systems(FixedLast, advance_playback.run_if(resource_exists::<Playback>));
+}
+
+fn gather_input(mut recorder: ResMut<Recorder>, input: Res<GameInput>) {
+    recorder.pending.merge(input.snapshot());
+}
+
+fn record_tick(mut commands: Commands, mut recorder: ResMut<Recorder>, input: Res<GameInput>) {
+    let tick = recorder.pending;
+    let actions = if tick.held.is_empty() {
+        "-".to_string()
+    } else {
+        tick.held.iter().map(GameAction::name).collect::<Vec<_>>().join(",")
+    };
+    let mut line = format!("{} {}", recorder.tick, actions);
+    if let Some(pointer) = tick.menu_pointer {
+        line.push(' ');
+        line.push_str(&pointer_text(pointer));
+    }
+    line.push('\n');
+    if let Err(error) = recorder.file.write_all(line.as_bytes()) {
+        warn!("Stopped recording: {}", error);
+        commands.remove_resource::<Recorder>();
+        return;
+    }
+    recorder.tick += 1;
+    // A second tick in the same frame still has the keys held, but not the click.
+    recorder.pending = TickInput {
+        held: input.snapshot().held,
+        menu_pointer: None,
+    };
+}
+
+// Gives every frame just enough time for one fixed tick at the current game speed, so
+// each recorded tick gets a frame of its own.
+fn replay_frame_time(
+    mut commands: Commands,
+    playback: Res<Playback>,
+    fixed: Res<Time<Fixed>>,
+    virtual_time: Res<Time<Virtual>>,
+    mut strategy: ResMut<TimeUpdateStrategy>,
+) {
+    match playback.current() {
+        Some(_) => {
+            let to_next_tick = fixed.timestep().saturating_sub(fixed.overstep());
+            let delta = to_next_tick.div_f64(virtual_time.relative_speed_f64()) + TICK_SLACK;
+            *strategy = TimeUpdateStrategy::ManualDuration(delta);
+        }
+        None => {
+            // Out of recording; hand the clock and the controls back to the player.
+            info!("Replay finished after {} ticks", playback.ticks.len());
+            *strategy = TimeUpdateStrategy::Automatic;
+            commands.remove_resource::<Playback>();
+        }
+    }
+}
+
+// Recorded input stands in for whatever the devices and the UI reported this tick.
+fn replay_input(playback: Res<Playback>, mut input: ResMut<GameInput>) {
+    if let Some(tick) = playback.current() {
+        input.replace(*tick);
+    }
+}
+
+fn advance_playback(mut playback: ResMut<Playback>) {
+    playback.next += 1;
+}
@@ -0,0 +1,79 @@
+use bevy::prelude::*;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// Small xorshift generator shared by every gameplay system, so a night can be
+// reproduced from its seed.
+#[derive(Resource)]
+pub struct GameRng {
+    state: u64,
+}
+
+impl Default for GameRng {
+    fn default() -> Self {
+        Self::seeded(Self::random_seed())
+    }
+}
+
+impl GameRng {
+    // A fresh seed from the clock, for nights that are not being replayed.
+    pub fn random_seed() -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_nanos() as u64)
+            .unwrap_or(0x9E37_79B9_7F4A_7C15)
+    }
+
+    pub fn seeded(seed: u64) -> Self {
+        // Xorshift gets stuck on zero.
+        Self { state: seed.max(1) }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        let mut x = self.state;
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        self.state = x;
+        x
+    }
+
+    // Uniform in [0, 1).
+    pub fn next_f32(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    pub fn chance(&mut self, probability: f32) -> bool {
+        self.next_f32() < probability
+    }
+
+    pub fn range(&mut self, len: usize) -> usize {
+        if len == 0 {
+            return 0;
+        }
+        (self.next_u64() % len as u64) as usize
+    }
+
+    pub fn pick<'a, T>(&mut self, items: &'a [T]) -> Option<&'a T> {
+        if items.is_empty() {
+            return None;
+        }
+        let index = self.range(items.len());
+        items.get(index)
+    }
+
+    pub fn pick_weighted<'a, T>(&mut self, items: &'a [(T, u32)]) -> Option<&'a T> {
+        let total: u32 = items.iter().map(|(_, weight)| weight).sum();
+        if total == 0 {
+            return None;
+        }
+
+        let mut roll = (self.next_u64() % total as u64) as u32;
+        for (item, weight) in items {
+            if roll < *weight {
+                return Some(item);
+            }
+            roll -= weight;
+        }
+        None
+    }
+}
@@ -0,0 +1,107 @@
+use bevy::prelude::*;
+use std::fs;
+
+pub struct SettingsPlugin;
+
+const SETTINGS_PATH: &str = "settings.cfg";
+
+// Player preferences, kept as `key = value` lines next to the executable.
+#[derive(Resource, Clone, Debug, PartialEq)]
+pub struct UserSettings {
+    pub master_volume: f64,
+    pub music_volume: f64,
+    pub sfx_volume: f64,
+    pub ui_volume: f64,
+    pub muted: bool,
+    pub language: String,
+    // Dialogue reveal speed in characters per second.
+    pub text_speed: f32,
+    // Multiplier on the simulation clock; 1.0 is normal speed.
+    pub game_speed: f32,
+}
+
+impl Default for UserSettings {
+    fn default() -> Self {
+        Self {
+            master_volume: 1.0,
+            music_volume: 1.0,
+            sfx_volume: 1.0,
+            ui_volume: 1.0,
+            muted: false,
+            language: "en-US".to_string(),
+            text_speed: 40.0,
+            game_speed: 1.0,
+        }
+    }
+}
+
+impl UserSettings {
+    pub fn load() -> Self {
+        match fs::read_to_string(SETTINGS_PATH) {
+            Ok(text) => Self::parse(&text),
+            Err(_) => Self::default(),
+        }
+    }
+
+    pub fn save(&self) {
+        if let Err(error) = fs::write(SETTINGS_PATH, self.to_text()) {
+            warn!("Could not save settings to {}: {}", SETTINGS_PATH, error);
+        }
+    }
+
+    fn parse(text: &str) -> Self {
+        let mut settings = Self::default();
+        for line in text.lines() {
+            let Some((key, value)) = line.split_once('=') else {
+                continue;
+            };
+            let (key, value) = (key.trim(), value.trim());
+            let volume = || value.parse::<f64>().ok().map(|v| v.clamp(0.0, 1.0));
+            match key {
+                "master_volume" => settings.master_volume = volume().unwrap_or(settings.master_volume),
+                "music_volume" => settings.music_volume = volume().unwrap_or(settings.music_volume),
+                "sfx_volume" => settings.sfx_volume = volume().unwrap_or(settings.sfx_volume),
+                "ui_volume" => settings.ui_volume = volume().unwrap_or(settings.ui_volume),
+                "muted" => settings.muted = value == "true",
+                "language" => settings.language = value.to_string(),
+                "text_speed" => {
+                    settings.text_speed = value
+                        .parse::<f32>()
+                        .ok()
+                        .filter(|speed| *speed > 0.0)
+                        .unwrap_or(settings.text_speed)
+                }
+                "game_speed" => {
+                    settings.game_speed = value
+                        .parse::<f32>()
+                        .ok()
+                        .filter(|speed| *speed > 0.0)
+                        .unwrap_or(settings.game_speed)
+                }
+                _ => warn!("Unknown setting '{}'", key),
+            }
+        }
+        settings
+    }
+
+    fn to_text(&self) -> String {
+        format!(
+            "master_volume = {:.2}\nmusic_volume = {:.2}\nsfx_volume = {:.2}\nui_volume = {:.2}\nmuted = {}\nlanguage = {}\ntext_speed = {:.0}\ngame_speed = {}\n",
+            self.master_volume, self.music_volume, self.sfx_volume, self.ui_volume, self.muted, self.language,
+            self.text_speed, self.game_speed
+        )
+    }
+}
+
+impl Plugin for SettingsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(UserSettings::load());
+        app.add_systems(Last, save_settings);
+    }
+}
+
+fn save_settings(settings: Res<UserSettings>) {
+    if settings.is_changed() && !settings.is_added() {
+        settings.save();
+    }
+}
@@ -0,0 +1,102 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+
+use super::harness::{TestApp, FRAME};
+use crate::energy::RESTED_STEP;
+use crate::night::{NightOutcome, NightReport};
+use crate::text::{GameTime, PopupQueue};
+use crate::{GameState, STEP};
+
+#[test]
+fn a_new_night_opens_the_intro_dialogue() {
+    let mut app = TestApp::new();
+    assert_eq!(app.state(), GameState::Menu);
+
+    app.enter_night();
+
+    assert_eq!(app.state(), GameState::Playing);
+    assert!(app.popup_active());
+    assert!(!app.resource::<PopupQueue>().is_empty());
+}
+
+#[test]
+fn pressing_right_for_one_step_moves_one_tile() {
+    let mut app = TestApp::new();
+    app.enter_night();
+    app.skip_dialogue();
+    let start = app.player_position();
+
+    app.press(KeyCode::ArrowRight);
+    app.advance(RESTED_STEP);
+    app.release(KeyCode::ArrowRight);
+    app.advance(RESTED_STEP);
+
+    assert_eq!(app.player_position(), start + Vec3::new(STEP, 0., 0.));
+}
+
+#[test]
+fn dialogue_holds_cliff_in_place() {
+    let mut app = TestApp::new();
+    app.enter_night();
+    let start = app.player_position();
+
+    app.press(KeyCode::ArrowRight);
+    app.advance(RESTED_STEP * 2);
+
+    assert!(app.popup_active());
+    assert_eq!(app.player_position(), start);
+}
+
+#[test]
+fn ten_seconds_advance_the_clock_one_minute() {
+    let mut app = TestApp::new();
+    // Entering the night is the first frame on the clock.
+    app.enter_night();
+    assert_eq!(app.resource::<GameTime>().minutes, 0);
+
+    app.advance(Duration::from_secs(10) - FRAME * 2);
+    assert_eq!(app.resource::<GameTime>().minutes, 0);
+
+    app.advance(FRAME * 2);
+    assert_eq!(app.resource::<GameTime>().hours, 5);
+    assert_eq!(app.resource::<GameTime>().minutes, 1);
+}
+
+#[test]
+fn escape_pauses_the_night() {
+    let mut app = TestApp::new();
+    app.enter_night();
+    app.skip_dialogue();
+
+    app.tap(KeyCode::Escape);
+
+    assert_eq!(app.state(), GameState::Paused);
+}
+
+#[test]
+fn the_end_of_the_shift_shows_the_results() {
+    let mut app = TestApp::new();
+    app.enter_night();
+    app.skip_dialogue();
+
+    // Three hours after the 5:00 start.
+    app.resource_mut::<GameTime>().hours = 8;
+    app.advance(Duration::from_secs(1));
+
+    assert_eq!(app.state(), GameState::Results);
+    assert_eq!(app.resource::<NightReport>().outcome, NightOutcome::ShiftOver);
+}
+
+#[test]
+fn double_speed_runs_the_clock_twice_as_fast() {
+    let mut app = TestApp::new();
+    app.resource_mut::<Time<Virtual>>().set_relative_speed(2.0);
+    app.enter_night();
+
+    app.advance(Duration::from_secs(5) - FRAME * 2);
+    assert_eq!(app.resource::<GameTime>().minutes, 0);
+
+    app.advance(FRAME * 2);
+    assert_eq!(app.resource::<GameTime>().minutes, 1);
+}
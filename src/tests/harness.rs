@@ -0,0 +1,142 @@
+use std::time::Duration;
+
+use bevy::asset::AssetPlugin;
+use bevy::input::keyboard::{Key, KeyboardInput, NativeKey};
+use bevy::input::{ButtonState, InputPlugin};
+use bevy::prelude::*;
+use bevy::state::app::StatesPlugin;
+use bevy::time::TimeUpdateStrategy;
+use bevy_ecs_tiled::prelude::TiledMap;
+use bevy_spritesheet_animation::prelude::*;
+use bevy_text_popup::TextPopupEvent;
+
+use crate::audio::{AudioMixer, CycleVolume, PlaySfx, ToggleMute};
+use crate::dialogue::AdvanceDialogue;
+use crate::mainmenu::MenuPlugin;
+use crate::music::SetMusicMood;
+use crate::settings::UserSettings;
+use crate::text::{PopupQueue, PopupState};
+use crate::{GamePlugin, GameState, PosVar};
+
+// Every update moves the clock on by one 60 fps frame.
+pub const FRAME: Duration = Duration::from_nanos(16_666_667);
+
+// Enough presses to get through any intro, with room to spare.
+const MAX_DIALOGUE_STEPS: usize = 200;
+
+pub struct TestApp {
+    app: App,
+}
+
+impl TestApp {
+    // The game as it sits on the title screen, without a window, renderer or audio device.
+    pub fn new() -> Self {
+        let mut app = App::new();
+        app.add_plugins((
+            MinimalPlugins,
+            StatesPlugin,
+            AssetPlugin::default(),
+            InputPlugin,
+            bevy_tweening::TweeningPlugin,
+            SpritesheetAnimationPlugin,
+        ))
+        .init_asset::<Image>()
+        .init_asset::<TextureAtlasLayout>()
+        .init_asset::<ColorMaterial>()
+        .init_asset::<TiledMap>()
+        .insert_resource(TimeUpdateStrategy::ManualDuration(FRAME))
+        // Defaults rather than whatever is in the player's settings file.
+        .insert_resource(UserSettings::default());
+
+        // Stand-ins for what the audio and popup plugins would provide.
+        app.init_resource::<AudioMixer>()
+            .add_event::<PlaySfx>()
+            .add_event::<CycleVolume>()
+            .add_event::<ToggleMute>()
+            .add_event::<SetMusicMood>()
+            .add_event::<TextPopupEvent>();
+
+        app.add_plugins((MenuPlugin, GamePlugin));
+        app.update();
+        Self { app }
+    }
+
+    pub fn update(&mut self) {
+        self.app.update();
+    }
+
+    // Runs as many frames as it takes to cover `duration`.
+    pub fn advance(&mut self, duration: Duration) {
+        let frames = duration.as_nanos().div_ceil(FRAME.as_nanos());
+        for _ in 0..frames {
+            self.app.update();
+        }
+    }
+
+    pub fn press(&mut self, key: KeyCode) {
+        self.send_key(key, ButtonState::Pressed);
+    }
+
+    pub fn release(&mut self, key: KeyCode) {
+        self.send_key(key, ButtonState::Released);
+    }
+
+    // Goes through the input plugin like a real key, so just_pressed works too.
+    fn send_key(&mut self, key: KeyCode, state: ButtonState) {
+        self.app.world_mut().send_event(KeyboardInput {
+            key_code: key,
+            logical_key: Key::Unidentified(NativeKey::Unidentified),
+            state,
+            window: Entity::PLACEHOLDER,
+        });
+    }
+
+    // Holds the key for one frame, then lets go.
+    pub fn tap(&mut self, key: KeyCode) {
+        self.press(key);
+        self.update();
+        self.release(key);
+        self.update();
+    }
+
+    // Starts a night straight away, skipping the fade. Takes one frame.
+    pub fn enter_night(&mut self) {
+        self.app
+            .world_mut()
+            .resource_mut::<NextState<GameState>>()
+            .set(GameState::Playing);
+        self.update();
+    }
+
+    // Clicks through every queued line until the player has control.
+    pub fn skip_dialogue(&mut self) {
+        for _ in 0..MAX_DIALOGUE_STEPS {
+            if !self.popup_active() && self.resource::<PopupQueue>().is_empty() {
+                return;
+            }
+            self.app.world_mut().send_event(AdvanceDialogue);
+            self.update();
+        }
+        panic!("dialogue was still open after {} advances", MAX_DIALOGUE_STEPS);
+    }
+
+    pub fn state(&self) -> GameState {
+        self.resource::<State<GameState>>().get().clone()
+    }
+
+    pub fn player_position(&self) -> Vec3 {
+        self.resource::<PosVar>().pos_vec
+    }
+
+    pub fn popup_active(&self) -> bool {
+        self.resource::<PopupState>().is_popup_active
+    }
+
+    pub fn resource<R: Resource>(&self) -> &R {
+        self.app.world().resource::<R>()
+    }
+
+    pub fn resource_mut<R: Resource>(&mut self) -> Mut<'_, R> {
+        self.app.world_mut().resource_mut::<R>()
+    }
+}
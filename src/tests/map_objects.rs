@@ -0,0 +1,31 @@
+use tiled::{Properties, PropertyValue};
+
+use crate::map_objects::ObjectProperties;
+
+fn properties(values: &[(&str, PropertyValue)]) -> Properties {
+    values.iter().map(|(name, value)| (name.to_string(), value.clone())).collect()
+}
+
+#[test]
+fn properties_are_read_as_the_field_type() {
+    let values = properties(&[
+        ("radius", PropertyValue::IntValue(48)),
+        ("room", PropertyValue::StringValue("Break Room".to_string())),
+        ("on", PropertyValue::BoolValue(false)),
+    ]);
+    let properties = ObjectProperties::new(&values);
+
+    assert_eq!(properties.require::<f32>("radius"), Ok(48.0));
+    assert_eq!(properties.get::<String>("room"), Ok(Some("Break Room".to_string())));
+    assert_eq!(properties.get_or("on", true), Ok(false));
+    assert_eq!(properties.get_or("patrol", 2), Ok(2));
+}
+
+#[test]
+fn wrong_or_missing_properties_are_errors() {
+    let values = properties(&[("patrol", PropertyValue::StringValue("far".to_string()))]);
+    let properties = ObjectProperties::new(&values);
+
+    assert!(properties.get::<i32>("patrol").unwrap_err().contains("should be an int"));
+    assert_eq!(properties.require::<String>("message"), Err("missing property 'message'".to_string()));
+}
@@ -0,0 +1,4 @@
+// Headless tests: the gameplay plugins on MinimalPlugins, driven by a manual clock.
+mod gameplay;
+mod harness;
+mod map_objects;
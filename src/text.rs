@@ -1,26 +1,54 @@
-use crate::audio::ButtonPress;
-use crate::audio::ButtonPressTriggered;
+use crate::dialogue::{DialogueLine, ShowDialogue, Speaker};
+use crate::locale::Locale;
+use crate::mess::MessLevel;
+use crate::night::CurrentLevel;
+use crate::InNight;
 use bevy::prelude::*;
-use bevy_input::keyboard::KeyboardInput;
-use bevy_kira_audio::prelude::*;
 use bevy_text_popup::TextPopupTimeout::Seconds;
-use bevy_text_popup::{
-    TextPopupButton, TextPopupEvent, TextPopupLocation, TextPopupPlugin, TextPopupTimeout,
-};
+use bevy_text_popup::{TextPopupEvent, TextPopupLocation};
+
+pub enum QueuedMessage {
+    // Story text, typed out in the dialogue panel.
+    Line(DialogueLine),
+    // Notices from the game, shown under the top bar without stopping play.
+    System(String),
+}
 
 #[derive(Resource)]
 pub struct PopupQueue {
-    messages: Vec<String>,
+    messages: Vec<QueuedMessage>,
 }
 
-#[derive(Resource)]
-pub struct PopupState {
-    pub is_popup_active: bool,
+impl PopupQueue {
+    // Messages are popped from the back, so new ones wait behind the rest.
+    pub fn push(&mut self, message: impl Into<String>) {
+        self.messages.insert(0, QueuedMessage::System(message.into()));
+    }
+
+    #[cfg(test)]
+    pub fn is_empty(&self) -> bool {
+        self.messages.is_empty()
+    }
+
+    // The waiting messages' text, next one first. For the dev inspector.
+    #[cfg(feature = "dev")]
+    pub fn previews(&self) -> impl Iterator<Item = &str> {
+        self.messages.iter().rev().map(|message| match message {
+            QueuedMessage::Line(line) => line.text.as_str(),
+            QueuedMessage::System(text) => text.as_str(),
+        })
+    }
+
+    pub fn clear(&mut self) {
+        self.messages.clear();
+    }
 }
 
-#[derive(Resource)]
-pub struct ButtonPressState {
-    pub triggered: bool,
+// Exists from startup so dialogue systems can run on the title screen; welcome_setup
+// resets it for every night.
+#[derive(Resource, Default)]
+pub struct PopupState {
+    pub is_popup_active: bool,
 }
 
 #[derive(Resource)]
@@ -29,145 +57,164 @@ pub struct GameTime {
     pub minutes: u32,
 }
 
-pub fn welcome_setup(mut commands: Commands) {
+// Simulated seconds per in-game minute.
+const SECONDS_PER_MINUTE: f32 = 10.0;
+
+// Kept apart from GameTime so the clock only reads as changed when a minute passes.
+#[derive(Resource)]
+pub struct MinuteTimer(Timer);
+
+impl Default for MinuteTimer {
+    fn default() -> Self {
+        Self(Timer::from_seconds(SECONDS_PER_MINUTE, TimerMode::Repeating))
+    }
+}
+
+impl GameTime {
+    // Minutes since the shift started at 5:00 p.m.
+    pub fn elapsed_minutes(&self) -> u32 {
+        ((self.hours + 12 - 5) % 12) * 60 + self.minutes
+    }
+}
+
+pub fn welcome_setup(mut commands: Commands, locale: Res<Locale>) {
+    let game_time = GameTime {
+        hours: 5,
+        minutes: 0,
+    };
     let messages = vec![
-        "It is 5:00 p.m.".to_string(),
-        "Everyone has left the office.".to_string(),
-        "You are an old janitor, Cliff.".to_string(),
-        "Your job is to clean the office.".to_string(),
-        "Use the arrow keys to move around.".to_string(),
-        "By the end of the night, the office might not just be clean.".to_string(),
-        "It might be yours.".to_string(),
+        DialogueLine::narrator(locale.format("welcome-time", &[("time", &locale.clock(&game_time))])),
+        DialogueLine::narrator(locale.get("welcome-alone")),
+        DialogueLine::narrator(locale.get("welcome-cliff")),
+        DialogueLine::new(Speaker::Cliff, locale.get("welcome-cliff-reply")),
+        DialogueLine::narrator(locale.get("welcome-job")),
+        DialogueLine::narrator(locale.get("welcome-controls")),
+        DialogueLine::narrator(locale.get("welcome-hint")),
+        DialogueLine::narrator(locale.get("welcome-twist")),
     ];
     commands.insert_resource(PopupQueue {
-        messages: messages.into_iter().rev().collect(),
+        messages: messages.into_iter().rev().map(QueuedMessage::Line).collect(),
     });
     commands.insert_resource(PopupState {
         is_popup_active: false,
     });
-    commands.insert_resource(ButtonPressState { triggered: false });
-    commands.insert_resource(GameTime {
-        hours: 5,
-        minutes: 0,
-    });
+    commands.insert_resource(game_time);
+    commands.insert_resource(MinuteTimer::default());
+}
+
+// Gameplay input only runs while no dialogue line is waiting on the player.
+pub fn no_modal_open(popup_state: Option<Res<PopupState>>) -> bool {
+    !popup_state.is_some_and(|state| state.is_popup_active)
 }
 
 pub fn handle_next_popup(
+    mut show_dialogue: EventWriter<ShowDialogue>,
     mut text_popup_events: EventWriter<TextPopupEvent>,
     mut popup_queue: ResMut<PopupQueue>,
     mut popup_state: ResMut<PopupState>,
-    //keys: Res<ButtonInput<KeyCode>>,
-    button_press: Res<AudioChannel<ButtonPress>>,
-    asset_server: Res<AssetServer>,
-    audio: Res<Audio>,
-    mut button_press_state: ResMut<ButtonPressState>,
 ) {
     if popup_state.is_popup_active {
         return;
     }
 
     if let Some(next_message) = popup_queue.messages.pop() {
-        popup_state.is_popup_active = true;
-        trigger_popup(
-            &mut text_popup_events,
-            &next_message,
-            button_press,
-            asset_server,
-            audio,
-        );
+        match next_message {
+            QueuedMessage::Line(line) => {
+                popup_state.is_popup_active = true;
+                show_dialogue.send(ShowDialogue(line));
+            }
+            // Notices wait behind story lines but never block the player.
+            QueuedMessage::System(text) => notice_popup(&mut text_popup_events, &text),
+        }
     }
 }
 
-pub fn trigger_popup(
-    text_popup_events: &mut EventWriter<TextPopupEvent>,
-    content: &str,
-    button_press: Res<AudioChannel<ButtonPress>>,
-    asset_server: Res<AssetServer>,
-    audio: Res<Audio>,
-) {
-    let event = TextPopupEvent {
-        location: (TextPopupLocation::Center),
-        content: content.to_string(),
-        background_color: Color::BLACK.with_alpha(0.9),
-        border_color: Color::BLACK.with_alpha(0.0),
-
-        confirm_button: Some(TextPopupButton {
-            font_size: 18.0,
-            text: "->".to_string(),
-            action: |commands, root_entity| {
-                commands.insert_resource(ButtonPressState { triggered: true });
-                commands.entity(root_entity).despawn_recursive();
-                commands.insert_resource(PopupState {
-                    is_popup_active: false,
-                });
-            },
-            ..Default::default()
-        }),
-        ..Default::default()
-    };
-    text_popup_events.send(event);
-}
+// How long a notice stays up before it goes away on its own.
+const NOTICE_SECONDS: u32 = 4;
 
-pub fn game_ui(mut commands: Commands, mut text_popup_events: EventWriter<TextPopupEvent>) {
+// A notice under the top bar that never waits on the player or holds up their input.
+pub fn notice_popup(text_popup_events: &mut EventWriter<TextPopupEvent>, content: &str) {
     text_popup_events.send(TextPopupEvent {
-        content: "TASKS COMPLETED: 0".to_string(),
-        font_size: 25.0,
-        background_color: Color::BLACK.with_alpha(0.0),
+        content: content.to_string(),
+        font_size: 20.0,
+        background_color: Color::BLACK.with_alpha(0.8),
         border_color: Color::BLACK.with_alpha(0.0),
-        location: TextPopupLocation::TopLeft,
-        padding: UiRect {
-            left: Val::Px(200.0),
-            right: Val::Px(20.0),
-            top: Val::Px(5.0),
-            bottom: Val::Px(10.0),
-        },
+        location: TextPopupLocation::Top,
+        margin: UiRect::top(Val::Px(48.0)),
+        padding: UiRect::axes(Val::Px(16.0), Val::Px(8.0)),
+        timeout: Seconds(NOTICE_SECONDS),
         ..default()
     });
+}
 
-    text_popup_events.send(TextPopupEvent {
-        content: "TIME: 05:00 P.M.".to_string(),
-        font_size: 25.0,
-        background_color: Color::BLACK.with_alpha(0.0),
-        border_color: Color::BLACK.with_alpha(0.0),
-        location: TextPopupLocation::TopRight,
-        padding: UiRect {
-            left: Val::Px(20.0),
-            right: Val::Px(175.0),
-            top: Val::Px(5.0),
-            bottom: Val::Px(10.0),
-        },
-        timeout: Seconds(10),
+#[derive(Component)]
+pub struct HudTasks;
 
-        ..default()
-    });
+#[derive(Component)]
+pub struct HudTime;
 
-    text_popup_events.send(TextPopupEvent {
-        content: "LEVEL 1".to_string(),
-        font_size: 25.0,
-        background_color: Color::BLACK.with_alpha(0.0),
-        border_color: Color::BLACK.with_alpha(0.0),
-        location: TextPopupLocation::Top,
-        padding: UiRect {
-            left: Val::Px(50.0),
-            right: Val::Px(20.0),
-            top: Val::Px(5.0),
-            bottom: Val::Px(10.0),
-        },
-        ..default()
-    });
+// The top bar: tasks on the left, the level in the middle and the clock on the right.
+pub fn game_ui(
+    mut commands: Commands,
+    locale: Res<Locale>,
+    game_time: Res<GameTime>,
+    level: Res<CurrentLevel>,
+) {
+    let total = level.config().tasks_required;
+    let hud_text = |content: String| {
+        TextBundle::from_section(
+            content,
+            TextStyle {
+                font_size: 25.0,
+                color: Color::WHITE,
+                ..default()
+            },
+        )
+    };
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    width: Val::Percent(100.0),
+                    justify_content: JustifyContent::SpaceBetween,
+                    padding: UiRect {
+                        left: Val::Px(200.0),
+                        right: Val::Px(175.0),
+                        top: Val::Px(5.0),
+                        bottom: Val::Px(10.0),
+                    },
+                    ..default()
+                },
+                ..default()
+            },
+            StateScoped(InNight),
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                hud_text(locale.format("hud-tasks", &[("count", &0), ("total", &total)])),
+                HudTasks,
+            ));
+            parent.spawn(hud_text(locale.format("hud-level", &[("level", &level.number())])));
+            parent.spawn((
+                hud_text(locale.format("hud-time", &[("time", &locale.clock(&game_time))])),
+                HudTime,
+            ));
+        });
 }
 
+// Runs on FixedUpdate, so the clock keeps the same pace at any frame rate or game speed.
 pub fn update_time(
     time: Res<Time>,
+    mut timer: ResMut<MinuteTimer>,
     mut game_time: ResMut<GameTime>,
-    mut text_popup_events: EventWriter<TextPopupEvent>,
-    mut time_tracker: Local<f32>,
+    locale: Res<Locale>,
+    mut clocks: Query<&mut Text, With<HudTime>>,
 ) {
-    *time_tracker += time.delta_seconds();
-
-    if *time_tracker >= 10.0 {
-        *time_tracker = 0.0;
+    timer.0.tick(time.delta());
 
+    for _ in 0..timer.0.times_finished_this_tick() {
         game_time.minutes += 1;
         if game_time.minutes >= 60 {
             game_time.minutes = 0;
@@ -177,22 +224,26 @@ pub fn update_time(
             }
         }
 
-        let time_str = format!("TIME: {:02}:{:02} P.M.", game_time.hours, game_time.minutes);
-
-        text_popup_events.send(TextPopupEvent {
-            content: time_str,
-            font_size: 25.0,
-            background_color: Color::BLACK.with_alpha(0.0),
-            border_color: Color::BLACK.with_alpha(0.0),
-            location: TextPopupLocation::TopRight,
-            padding: UiRect {
-                left: Val::Px(20.0),
-                right: Val::Px(175.0),
-                top: Val::Px(5.0),
-                bottom: Val::Px(10.0),
-            },
-            timeout: Seconds(10),
-            ..default()
-        });
+        let time_str = locale.format("hud-time", &[("time", &locale.clock(&game_time))]);
+        for mut clock in &mut clocks {
+            clock.sections[0].value = time_str.clone();
+        }
+    }
+}
+
+pub fn update_tasks(
+    mess_level: Res<MessLevel>,
+    level: Res<CurrentLevel>,
+    locale: Res<Locale>,
+    mut counters: Query<&mut Text, With<HudTasks>>,
+) {
+    if !mess_level.is_changed() {
+        return;
+    }
+
+    let total = level.config().tasks_required;
+    let tasks_str = locale.format("hud-tasks", &[("count", &mess_level.cleaned), ("total", &total)]);
+    for mut counter in &mut counters {
+        counter.sections[0].value = tasks_str.clone();
     }
 }
@@ -0,0 +1,62 @@
+use bevy::prelude::*;
+
+use crate::settings::UserSettings;
+
+pub struct TimeScalePlugin;
+
+// Speeds offered in the settings menu, in cycling order.
+const GAME_SPEEDS: [f32; 5] = [1.0, 2.0, 4.0, 0.25, 0.5];
+
+// Steps to the next speed in GAME_SPEEDS.
+#[derive(Event, Clone)]
+pub struct CycleGameSpeed;
+
+// Jumps straight to a speed, for testing.
+#[derive(Event, Clone)]
+pub struct SetGameSpeed(pub f32);
+
+impl Plugin for TimeScalePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<CycleGameSpeed>();
+        app.add_event::<SetGameSpeed>();
+        app.add_systems(Startup, apply_saved_speed);
+        app.add_systems(Update, change_game_speed);
+    }
+}
+
+// Virtual time drives FixedUpdate, tweens and timers alike, so scaling it scales the
+// whole simulation in one place.
+fn set_speed(time: &mut Time<Virtual>, speed: f32) {
+    time.set_relative_speed(speed);
+    info!("Game speed: {}x", speed);
+}
+
+fn apply_saved_speed(settings: Res<UserSettings>, mut time: ResMut<Time<Virtual>>) {
+    if settings.game_speed != 1.0 {
+        set_speed(&mut time, settings.game_speed);
+    }
+}
+
+fn change_game_speed(
+    mut cycles: EventReader<CycleGameSpeed>,
+    mut sets: EventReader<SetGameSpeed>,
+    mut time: ResMut<Time<Virtual>>,
+    mut settings: ResMut<UserSettings>,
+) {
+    for _ in cycles.read() {
+        let current = GAME_SPEEDS
+            .iter()
+            .position(|speed| *speed == time.relative_speed())
+            .unwrap_or(0);
+        let speed = GAME_SPEEDS[(current + 1) % GAME_SPEEDS.len()];
+        set_speed(&mut time, speed);
+        settings.game_speed = speed;
+    }
+    for SetGameSpeed(speed) in sets.read() {
+        if *speed <= 0.0 {
+            warn!("Ignoring game speed {}", speed);
+            continue;
+        }
+        set_speed(&mut time, *speed);
+    }
+}
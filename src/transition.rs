@@ -0,0 +1,255 @@
+use bevy::input::InputSystem;
+use bevy::prelude::*;
+use bevy::ui::FocusPolicy;
+use bevy_tweening::lens::{UiBackgroundColorLens, UiPositionLens};
+use bevy_tweening::*;
+use std::time::Duration;
+
+use crate::input::GameInputSet;
+use crate::GameState;
+
+pub struct TransitionPlugin;
+
+// Each half of a transition: covering the old screen, then revealing the new one.
+const HALF_DURATION: Duration = Duration::from_millis(400);
+const OVERLAY_COLOR: Color = Color::BLACK;
+
+// Tween completion ids.
+pub const TRANSITION_COVERED: u64 = 2;
+pub const TRANSITION_REVEALED: u64 = 3;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TransitionStyle {
+    Fade,
+    Slide,
+}
+
+impl TransitionStyle {
+    // Menu screens slide into each other and pausing cuts straight to the pause menu;
+    // anything else fades through black. `None` means no transition at all.
+    fn between(from: &GameState, to: &GameState) -> Option<Self> {
+        let is_menu = |state: &GameState| matches!(state, GameState::Menu | GameState::Settings);
+        let is_pause = |state: &GameState| matches!(state, GameState::Playing | GameState::Paused);
+        if is_menu(from) && is_menu(to) {
+            Some(TransitionStyle::Slide)
+        } else if is_pause(from) && is_pause(to) {
+            None
+        } else {
+            Some(TransitionStyle::Fade)
+        }
+    }
+}
+
+// Request a state change. The state only switches once the screen is covered.
+#[derive(Event, Clone)]
+pub struct TransitionTo {
+    pub state: GameState,
+}
+
+impl TransitionTo {
+    pub fn new(state: GameState) -> Self {
+        Self { state }
+    }
+}
+
+#[derive(Default)]
+enum TransitionPhase {
+    #[default]
+    Idle,
+    Covering { to: GameState, style: TransitionStyle },
+    Revealing,
+}
+
+#[derive(Resource, Default)]
+pub struct Transition {
+    phase: TransitionPhase,
+    overlay: Option<Entity>,
+}
+
+impl Transition {
+    pub fn is_running(&self) -> bool {
+        !matches!(self.phase, TransitionPhase::Idle)
+    }
+
+    // The state being transitioned to, before the switch has happened.
+    pub fn destination(&self) -> Option<&GameState> {
+        match &self.phase {
+            TransitionPhase::Covering { to, .. } => Some(to),
+            _ => None,
+        }
+    }
+
+    pub fn total_duration(&self) -> Duration {
+        HALF_DURATION * 2
+    }
+}
+
+pub fn transition_idle(transition: Res<Transition>) -> bool {
+    !transition.is_running()
+}
+
+#[derive(Component)]
+struct TransitionOverlay;
+
+impl Plugin for TransitionPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Transition>();
+        app.add_event::<TransitionTo>();
+        app.add_systems(PreUpdate, hold_input.after(InputSystem).before(GameInputSet));
+        // After Update, so a request sent from any system there starts the same frame.
+        app.add_systems(PostUpdate, (start_transition, advance_transition).chain());
+    }
+}
+
+fn overlay_position(left: f32) -> UiRect {
+    UiRect {
+        left: Val::Percent(left),
+        top: Val::Px(0.),
+        ..default()
+    }
+}
+
+fn start_transition(
+    mut commands: Commands,
+    mut requests: EventReader<TransitionTo>,
+    mut transition: ResMut<Transition>,
+    state: Res<State<GameState>>,
+    mut game_state: ResMut<NextState<GameState>>,
+) {
+    for request in requests.read() {
+        if transition.is_running() {
+            info!("Ignoring transition to {:?}, one is already running", request.state);
+            continue;
+        }
+        if request.state == *state.get() {
+            continue;
+        }
+
+        let Some(style) = TransitionStyle::between(state.get(), &request.state) else {
+            game_state.set(request.state.clone());
+            continue;
+        };
+        let mut overlay = commands.spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    width: Val::Percent(100.),
+                    height: Val::Percent(100.),
+                    ..default()
+                },
+                // Swallows clicks so buttons underneath stay inert.
+                focus_policy: FocusPolicy::Block,
+                z_index: ZIndex::Global(i32::MAX),
+                ..default()
+            },
+            TransitionOverlay,
+        ));
+
+        match style {
+            TransitionStyle::Fade => {
+                let tween = Tween::new(
+                    EaseFunction::QuadraticInOut,
+                    HALF_DURATION,
+                    UiBackgroundColorLens {
+                        start: OVERLAY_COLOR.with_alpha(0.),
+                        end: OVERLAY_COLOR,
+                    },
+                )
+                .with_completed_event(TRANSITION_COVERED);
+                overlay.insert((
+                    BackgroundColor(OVERLAY_COLOR.with_alpha(0.)),
+                    Animator::new(tween),
+                ));
+            }
+            TransitionStyle::Slide => {
+                let tween = Tween::new(
+                    EaseFunction::QuadraticInOut,
+                    HALF_DURATION,
+                    UiPositionLens {
+                        start: overlay_position(100.),
+                        end: overlay_position(0.),
+                    },
+                )
+                .with_completed_event(TRANSITION_COVERED);
+                overlay.insert((BackgroundColor(OVERLAY_COLOR), Animator::new(tween)));
+            }
+        }
+
+        transition.overlay = Some(overlay.id());
+        transition.phase = TransitionPhase::Covering {
+            to: request.state.clone(),
+            style,
+        };
+    }
+}
+
+fn advance_transition(
+    mut commands: Commands,
+    mut completed: EventReader<TweenCompleted>,
+    mut transition: ResMut<Transition>,
+    mut game_state: ResMut<NextState<GameState>>,
+) {
+    for event in completed.read() {
+        if Some(event.entity) != transition.overlay {
+            continue;
+        }
+
+        match (event.user_data, &transition.phase) {
+            (TRANSITION_COVERED, TransitionPhase::Covering { to, style }) => {
+                // The old screen is hidden; swap states underneath and uncover the new one.
+                game_state.set(to.clone());
+                let mut overlay = commands.entity(event.entity);
+                match style {
+                    TransitionStyle::Fade => {
+                        overlay.insert(Animator::new(
+                            Tween::new(
+                                EaseFunction::QuadraticInOut,
+                                HALF_DURATION,
+                                UiBackgroundColorLens {
+                                    start: OVERLAY_COLOR,
+                                    end: OVERLAY_COLOR.with_alpha(0.),
+                                },
+                            )
+                            .with_completed_event(TRANSITION_REVEALED),
+                        ));
+                    }
+                    TransitionStyle::Slide => {
+                        overlay.insert(Animator::new(
+                            Tween::new(
+                                EaseFunction::QuadraticInOut,
+                                HALF_DURATION,
+                                UiPositionLens {
+                                    start: overlay_position(0.),
+                                    end: overlay_position(-100.),
+                                },
+                            )
+                            .with_completed_event(TRANSITION_REVEALED),
+                        ));
+                    }
+                }
+                transition.phase = TransitionPhase::Revealing;
+            }
+            (TRANSITION_REVEALED, TransitionPhase::Revealing) => {
+                commands.entity(event.entity).despawn_recursive();
+                transition.overlay = None;
+                transition.phase = TransitionPhase::Idle;
+            }
+            _ => {}
+        }
+    }
+}
+
+// Keys and buttons pressed mid-transition never reach the screen behind it.
+fn hold_input(
+    transition: Res<Transition>,
+    mut keys: ResMut<ButtonInput<KeyCode>>,
+    mut mouse_buttons: ResMut<ButtonInput<MouseButton>>,
+    mut gamepad_buttons: ResMut<ButtonInput<GamepadButton>>,
+) {
+    if !transition.is_running() {
+        return;
+    }
+    keys.reset_all();
+    mouse_buttons.reset_all();
+    gamepad_buttons.reset_all();
+}